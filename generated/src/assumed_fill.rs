@@ -1,33 +1,91 @@
+use std::{cell::OnceCell, fmt::Write};
+
 use rand::prelude::*;
 use rand_pcg::Pcg64;
 use snafu::{ResultExt, Snafu};
 
 use crate::{
+    explain::Missing,
     explorer::{MultiworldExplorer, Placement},
-    generated::{Item, Location, Options},
-    individual_world::{generate_single_world, SingleWorld},
+    generated::{Item, Location, LogicMode, Options},
+    hints::generate_hints,
+    individual_world::{self, generate_single_world, SingleWorld},
     logic_static::Requirements,
-    plando,
+    playthrough::{minimal_playthrough, Playthrough},
 };
 
 #[derive(Debug, Snafu)]
 pub enum Error {
-    #[snafu(display("Cannot place item {} w{world} at {impossible_locations}", item.get().name))]
+    #[snafu(display(
+        "Cannot place item {} w{world} at any of:{}",
+        item.get().name,
+        display_unreachable(impossible_locations)
+    ))]
     NoLocationLeft {
-        impossible_locations: String,
+        // world, location, what it still needs
+        impossible_locations: Vec<(usize, Location, Vec<Missing>)>,
         world: usize,
         item: Item,
     },
+    #[snafu(display("{items} items left for only {locations} locations"))]
+    NotEnoughLocations { items: usize, locations: usize },
+}
+
+fn display_unreachable(locations: &[(usize, Location, Vec<Missing>)]) -> String {
+    let mut out = String::new();
+    for (world, location, missing) in locations {
+        let missing: Vec<_> = missing.iter().map(|missing| missing.to_string()).collect();
+        write!(
+            &mut out,
+            "\n  w{world} {}: needs {}",
+            location.get().display_name,
+            missing.join(", ")
+        )
+        .unwrap();
+    }
+    out
 }
 
 #[derive(Debug, Snafu)]
 pub enum CombinedError {
     #[snafu(display("w{world}: {source}"))]
-    Plando { source: plando::Error, world: usize },
+    World {
+        source: individual_world::Error,
+        world: usize,
+    },
     #[snafu(display("{stage}: {source}"))]
     Fill { source: Error, stage: &'static str },
 }
 
+impl CombinedError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::World { source, .. } => source.is_retryable(),
+            // the item and location counts are the same for every seed
+            Self::Fill {
+                source: Error::NotEnoughLocations { .. },
+                ..
+            } => false,
+            Self::Fill { .. } => true,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum RetryError {
+    #[snafu(display("attempt {attempt} (seed {seed}): {source}"))]
+    Hard {
+        source: CombinedError,
+        attempt: u32,
+        seed: u64,
+    },
+    #[snafu(display("no success after {attempts} attempts, last error: {source}"))]
+    TooManyAttempts {
+        source: CombinedError,
+        attempts: u32,
+    },
+}
+
 pub struct InputWorld<'a> {
     options: &'a Options,
     placement: &'a mut Placement,
@@ -52,66 +110,419 @@ pub fn assumed_fill_worlds<R: Rng>(
         .flat_map(|(world_id, world)| world.items.iter().map(move |item| (world_id, *item)))
         .collect();
     worlds_items.shuffle(rng);
-    'outer: while let Some((item_world, item)) = worlds_items.pop() {
-        let mut explorer = MultiworldExplorer::new();
-        for (worldid, world) in worlds.iter_mut().enumerate() {
-            explorer.add_world(
-                worldid,
-                None,
-                world.placement,
-                world.requirements,
-                world.options,
-            );
-        }
+    // without logic, every location is reachable, so items end up in random locations
+    let no_logic_worlds: Vec<bool> = worlds
+        .iter()
+        .map(|world| world.options.logic_mode == LogicMode::NoLogic)
+        .collect();
+    let all_no_logic = no_logic_worlds.iter().all(|no_logic| *no_logic);
+    let mut explorer = MultiworldExplorer::new();
+    for (worldid, world) in worlds.iter_mut().enumerate() {
+        explorer.add_world(
+            worldid,
+            None,
+            world.placement,
+            world.requirements,
+            world.options,
+        );
+    }
+    // the items are placed from the back, so the unplaced items are always the first ones
+    // snapshots[i] is explored with the first i items, instead of exploring from scratch for every item
+    let mut snapshots = Vec::new();
+    if !all_no_logic {
         for (unplaced_item_world, unplaced_item) in worlds_items.iter() {
+            explorer.explore();
+            snapshots.push(explorer.snapshot());
             explorer.insert_item(*unplaced_item_world, *unplaced_item);
         }
+    }
+    'outer: while let Some((item_world, item)) = worlds_items.pop() {
+        if let Some(snapshot) = snapshots.pop() {
+            explorer.restore(&snapshot);
+        }
         let mut loc_idx = 0;
         while let Some((location_world, location)) = worlds_locations.get(loc_idx) {
             // println!("checking {item:?} at {location:?}");
             // println!("{:?}", explorer.inventory);
-            if explorer.can_reach(*location_world, location) {
+            if no_logic_worlds[*location_world] || explorer.can_reach(*location_world, location) {
                 // println!("placing {:?} at {:?}", item, location);
-                worlds[*location_world]
-                    .placement
-                    .set_location(*location, item_world, item.into());
+                explorer.set_location(*location_world, *location, item_world, item.into());
                 worlds_locations.swap_remove(loc_idx);
                 continue 'outer;
             }
             loc_idx += 1;
         }
+        let impossible_locations = worlds_locations
+            .iter()
+            .map(|(location_world, location)| {
+                let missing = if no_logic_worlds[*location_world] {
+                    Vec::new()
+                } else {
+                    explorer.explain(*location_world, location)
+                };
+                (*location_world, *location, missing)
+            })
+            .collect();
         return Err(Error::NoLocationLeft {
-            impossible_locations: format!("{:?}", worlds_locations),
+            impossible_locations,
             world: item_world,
             item,
         });
     }
+    for (location_world, location, item_world, item) in explorer.take_placed() {
+        worlds[location_world]
+            .placement
+            .set_location(location, item_world, item);
+    }
+    for world in worlds.iter_mut() {
+        world
+            .locations
+            .retain(|loc| world.placement.get_item_at(*loc).is_none());
+    }
     Ok(())
 }
 
+/// places items at random locations of all worlds without checking logic,
+/// only for items that are never needed, like consumables
+pub fn random_fill_worlds<R: Rng>(
+    rng: &mut R,
+    mut worlds: Vec<InputWorld<'_>>,
+) -> Result<(), Error> {
+    let mut worlds_locations: Vec<_> = worlds
+        .iter()
+        .enumerate()
+        .flat_map(|(world_id, world)| world.locations.iter().map(move |loc| (world_id, *loc)))
+        .collect();
+    let worlds_items: Vec<_> = worlds
+        .iter()
+        .enumerate()
+        .flat_map(|(world_id, world)| world.items.iter().map(move |item| (world_id, *item)))
+        .collect();
+    if worlds_items.len() > worlds_locations.len() {
+        return Err(Error::NotEnoughLocations {
+            items: worlds_items.len(),
+            locations: worlds_locations.len(),
+        });
+    }
+    worlds_locations.shuffle(rng);
+    for ((item_world, item), (location_world, location)) in
+        worlds_items.into_iter().zip(worlds_locations)
+    {
+        worlds[location_world]
+            .placement
+            .set_location(location, item_world, item.into());
+    }
+    for world in worlds.iter_mut() {
+        world
+            .locations
+            .retain(|loc| world.placement.get_item_at(*loc).is_none());
+    }
+    Ok(())
+}
+
+/// the worlds of one seed, with everything placed
+pub struct FilledWorlds<'a> {
+    pub worlds: Vec<SingleWorld<'a>>,
+    playthrough: OnceCell<Option<Playthrough>>,
+}
+
+impl<'a> FilledWorlds<'a> {
+    /// the minimal playthrough, computed the first time the hints or the spoiler need it
+    /// `None` if the goal can't be reached
+    pub fn playthrough(&self) -> Option<&Playthrough> {
+        self.playthrough
+            .get_or_init(|| minimal_playthrough(&self.worlds))
+            .as_ref()
+    }
+}
+
 pub fn run_with_seed<'a>(
     seed: u64,
+    options: &Options,
     requirements: &'a Requirements<'static>,
     world_count: usize,
-) -> Result<Vec<SingleWorld<'a>>, CombinedError> {
+) -> Result<FilledWorlds<'a>, CombinedError> {
     let mut rng = Pcg64::seed_from_u64(seed);
 
     let mut worlds = Vec::new();
     for world in 0..world_count {
-        let single_world = generate_single_world(&mut rng, Options::default(), requirements, world)
-            .context(PlandoSnafu { world })?;
+        let single_world = generate_single_world(&mut rng, options.clone(), requirements, world)
+            .context(WorldSnafu { world })?;
         worlds.push(single_world);
     }
+    // progress items can only go into progress locations
+    let mut worlds_progress_locations: Vec<Vec<Location>> = worlds
+        .iter_mut()
+        .map(|world| {
+            let (progress_locations, other_locations) = world
+                .locations
+                .iter()
+                .partition(|loc| world.progress_locations.contains(loc));
+            world.locations = other_locations;
+            progress_locations
+        })
+        .collect();
+    let input_worlds: Vec<_> = worlds
+        .iter_mut()
+        .zip(worlds_progress_locations.iter_mut())
+        .map(|(world, progress_locations)| InputWorld {
+            items: world
+                .items
+                .iter()
+                .filter(|item| {
+                    world
+                        .item_meta
+                        .get(item)
+                        .is_some_and(|meta| meta.progress_loc)
+                })
+                .copied()
+                .collect(),
+            locations: progress_locations,
+            options: &world.options,
+            placement: &mut world.placement,
+            requirements: &world.world_requirements,
+        })
+        .collect();
+    assumed_fill_worlds(&mut rng, input_worlds).context(FillSnafu { stage: "progress" })?;
+    // progress locations that are left over are available for everything else
+    for (world, progress_locations) in worlds.iter_mut().zip(worlds_progress_locations) {
+        world.locations.extend(progress_locations);
+        world.locations.sort_unstable();
+    }
     let input_worlds: Vec<_> = worlds
         .iter_mut()
         .map(|world| InputWorld {
-            items: world.items.clone(),
+            items: world
+                .items
+                .iter()
+                .filter(|item| {
+                    !world
+                        .item_meta
+                        .get(item)
+                        .is_some_and(|meta| meta.progress_loc || meta.consumable)
+                })
+                .copied()
+                .collect(),
             locations: &mut world.locations,
             options: &world.options,
             placement: &mut world.placement,
             requirements: &world.world_requirements,
         })
         .collect();
-    assumed_fill_worlds(&mut rng, input_worlds).context(FillSnafu { stage: "progress" })?;
-    Ok(worlds)
+    assumed_fill_worlds(&mut rng, input_worlds).context(FillSnafu {
+        stage: "nonprogress",
+    })?;
+    // consumables are never needed, so they don't need logic
+    let input_worlds: Vec<_> = worlds
+        .iter_mut()
+        .map(|world| InputWorld {
+            items: world
+                .items
+                .iter()
+                .filter(|item| {
+                    world
+                        .item_meta
+                        .get(item)
+                        .is_some_and(|meta| meta.consumable)
+                })
+                .copied()
+                .collect(),
+            locations: &mut world.locations,
+            options: &world.options,
+            placement: &mut world.placement,
+            requirements: &world.world_requirements,
+        })
+        .collect();
+    random_fill_worlds(&mut rng, input_worlds).context(FillSnafu {
+        stage: "consumables",
+    })?;
+    let mut filled = FilledWorlds {
+        worlds,
+        playthrough: OnceCell::new(),
+    };
+    let hints = generate_hints(&mut rng, &filled);
+    for (world, hints) in filled.worlds.iter_mut().zip(hints) {
+        world.hints = hints;
+    }
+    Ok(filled)
+}
+
+pub struct RetriedWorlds<'a> {
+    pub filled: FilledWorlds<'a>,
+    /// the seed of the attempt that worked
+    pub seed: u64,
+    pub attempts: u32,
+    /// the errors of the failed attempts, in order
+    pub failed_attempts: Vec<CombinedError>,
+}
+
+/// the seed of every attempt, the first one is the user seed itself
+/// so seeds that work right away generate the same as with `run_with_seed`
+pub fn attempt_seeds(seed: u64) -> impl Iterator<Item = u64> {
+    let mut seed_rng = Pcg64::seed_from_u64(seed);
+    std::iter::once(seed).chain(std::iter::repeat_with(move || seed_rng.gen()))
+}
+
+/// runs `run_with_seed` with seeds derived from the user seed, until one works
+/// errors that fail with every seed (like settings conflicts) are returned immediately
+pub fn run_with_retries<'a>(
+    seed: u64,
+    options: &Options,
+    requirements: &'a Requirements<'static>,
+    world_count: usize,
+    max_attempts: u32,
+) -> Result<RetriedWorlds<'a>, RetryError> {
+    let mut failed_attempts = Vec::new();
+    for (attempt, attempt_seed) in (1..=max_attempts.max(1)).zip(attempt_seeds(seed)) {
+        match run_with_seed(attempt_seed, options, requirements, world_count) {
+            Ok(filled) => {
+                return Ok(RetriedWorlds {
+                    filled,
+                    seed: attempt_seed,
+                    attempts: attempt,
+                    failed_attempts,
+                })
+            }
+            Err(e) if !e.is_retryable() => {
+                return Err(RetryError::Hard {
+                    source: e,
+                    attempt,
+                    seed: attempt_seed,
+                })
+            }
+            Err(e) => failed_attempts.push(e),
+        }
+    }
+    Err(RetryError::TooManyAttempts {
+        // there is at least one attempt
+        source: failed_attempts.pop().unwrap(),
+        attempts: max_attempts.max(1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generated::{get_logic, BannedTypes},
+        plando,
+    };
+
+    /// the first seed that fails with the default options, together with its error
+    fn failing_seed(
+        options: &Options,
+        requirements: &Requirements<'static>,
+    ) -> (u64, CombinedError) {
+        (0..)
+            .find_map(|seed| {
+                run_with_seed(seed, options, requirements, 1)
+                    .err()
+                    .map(|e| (seed, e))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn attempt_seeds_are_deterministic() {
+        let seeds: Vec<_> = attempt_seeds(1234).take(20).collect();
+        assert_eq!(seeds[0], 1234);
+        assert_eq!(seeds, attempt_seeds(1234).take(20).collect::<Vec<_>>());
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), seeds.len());
+        // the derived seeds depend on the user seed
+        assert_ne!(
+            seeds[1..],
+            attempt_seeds(1235).skip(1).take(19).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let world = |source| CombinedError::World { source, world: 0 };
+        let plando = |source| world(individual_world::Error::Plando { source });
+        assert!(plando(plando::Error::PlandoNoLocation).is_retryable());
+        assert!(!plando(plando::Error::PlandoEntryConflict).is_retryable());
+        assert!(!plando(plando::Error::NoProgressLocation { item: "Clawshots" }).is_retryable());
+        assert!(world(individual_world::Error::Unbeatable).is_retryable());
+        assert!(world(individual_world::Error::DeadStart { reachable: 0 }).is_retryable());
+        assert!(!world(individual_world::Error::UncheckedTrialEntrances).is_retryable());
+        assert!(!world(individual_world::Error::TooManyItems {
+            items: 2,
+            locations: 1
+        })
+        .is_retryable());
+        assert!(!CombinedError::Fill {
+            source: Error::NotEnoughLocations {
+                items: 2,
+                locations: 1
+            },
+            stage: "progress",
+        }
+        .is_retryable());
+        assert!(CombinedError::Fill {
+            source: Error::NoLocationLeft {
+                impossible_locations: Vec::new(),
+                world: 0,
+                item: Item::Clawshots,
+            },
+            stage: "progress",
+        }
+        .is_retryable());
+    }
+
+    #[test]
+    fn retries_with_derived_seeds() {
+        let options = Options::default();
+        let requirements = get_logic(&options);
+        let (seed, error) = failing_seed(&options, &requirements);
+        assert!(error.is_retryable(), "{error}");
+
+        let retried = run_with_retries(seed, &options, &requirements, 1, 50).unwrap();
+        let attempts = retried.attempts as usize;
+        assert!(attempts > 1);
+        assert_eq!(retried.failed_attempts.len(), attempts - 1);
+        assert_eq!(Some(retried.seed), attempt_seeds(seed).nth(attempts - 1));
+        // the same attempt seed always generates the same seed
+        let again = run_with_seed(retried.seed, &options, &requirements, 1).unwrap();
+        assert_eq!(
+            retried.filled.worlds[0].placement.locations,
+            again.worlds[0].placement.locations
+        );
+        let again = run_with_retries(seed, &options, &requirements, 1, 50).unwrap();
+        assert_eq!(again.seed, retried.seed);
+        assert_eq!(
+            retried.filled.worlds[0].placement.locations,
+            again.filled.worlds[0].placement.locations
+        );
+
+        match run_with_retries(seed, &options, &requirements, 1, 1) {
+            Err(RetryError::TooManyAttempts { attempts: 1, .. }) => (),
+            Err(e) => panic!("{e}"),
+            Ok(_) => panic!("seed {seed} worked with one attempt"),
+        }
+    }
+
+    #[test]
+    fn settings_conflicts_are_not_retried() {
+        // the lanayru caves small key can only be at lanayru checks
+        let options = Options {
+            banned_types: [BannedTypes::Lanayru].into_iter().collect(),
+            ..Options::default()
+        };
+        let requirements = get_logic(&options);
+        match run_with_retries(7, &options, &requirements, 1, 50) {
+            Err(RetryError::Hard {
+                source,
+                attempt,
+                seed,
+            }) => {
+                assert!(!source.is_retryable());
+                assert_eq!((attempt, seed), (1, 7));
+            }
+            Err(e) => panic!("{e}"),
+            Ok(_) => panic!("banning lanayru worked"),
+        }
+    }
 }
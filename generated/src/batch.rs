@@ -0,0 +1,351 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    ops::Range,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use snafu::{OptionExt, ResultExt, Snafu};
+
+use crate::{
+    assumed_fill::{run_with_retries, RetriedWorlds, RetryError},
+    generated::{Item, Location, Options},
+    individual_world::collect_spheres,
+    logic_static::{Requirements, SetOptionError},
+};
+
+#[derive(Debug, Snafu)]
+pub enum OptionsError {
+    #[snafu(display("Cannot read options file {path}: {source}"))]
+    ReadFile { source: io::Error, path: String },
+    #[snafu(display("{path}:{line}: expected `command: value`"))]
+    FileSyntax { path: String, line: usize },
+    #[snafu(display("{path}:{line}: {source}"))]
+    FileOption {
+        source: SetOptionError,
+        path: String,
+        line: usize,
+    },
+    #[snafu(display("--{flag}: {source}"))]
+    FlagOption {
+        source: SetOptionError,
+        flag: String,
+    },
+}
+
+/// starts with the default options and applies `--options=<file>` and `--<command>=<value>`
+/// flags in order, a flag without a value sets a boolean option
+/// the file has one `command: value` line per option, `#` starts a comment
+pub fn options_from_flags<'a>(
+    flags: impl IntoIterator<Item = &'a str>,
+) -> Result<Options, OptionsError> {
+    let mut options = Options::default();
+    for flag in flags {
+        let flag = flag.trim_start_matches("--");
+        let (command, value) = flag.split_once('=').unwrap_or((flag, "true"));
+        if command == "options" {
+            let path = value;
+            let text = fs::read_to_string(path).context(ReadFileSnafu { path })?;
+            for (index, line) in text.lines().enumerate() {
+                let line = line.split('#').next().unwrap().trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (command, value) = line.split_once(':').context(FileSyntaxSnafu {
+                    path,
+                    line: index + 1,
+                })?;
+                options
+                    .set(command.trim(), value.trim())
+                    .context(FileOptionSnafu {
+                        path,
+                        line: index + 1,
+                    })?;
+            }
+        } else {
+            options
+                .set(command, value)
+                .context(FlagOptionSnafu { flag })?;
+        }
+    }
+    Ok(options)
+}
+
+/// the outcome of generating one seed
+pub struct SeedResult {
+    pub seed: u64,
+    pub time: Duration,
+    /// the error message, if generating failed
+    pub error: Option<String>,
+    /// (location, item) for every item in every world
+    pub placements: Vec<(Location, Item)>,
+    pub sphere_count: usize,
+    pub attempts: u32,
+}
+
+pub fn generate_seed(
+    seed: u64,
+    options: &Options,
+    requirements: &Requirements<'static>,
+    world_count: usize,
+    max_attempts: u32,
+) -> SeedResult {
+    let start = Instant::now();
+    let result = run_with_retries(seed, options, requirements, world_count, max_attempts);
+    let time = start.elapsed();
+    match result {
+        Ok(RetriedWorlds {
+            filled, attempts, ..
+        }) => SeedResult {
+            seed,
+            time,
+            error: None,
+            placements: filled
+                .worlds
+                .iter()
+                .flat_map(|world| world.placement.locations.iter())
+                .filter_map(|(location, (_, item))| item.as_item().map(|item| (*location, item)))
+                .collect(),
+            sphere_count: collect_spheres(&filled.worlds).len(),
+            attempts,
+        },
+        Err(e) => SeedResult {
+            seed,
+            time,
+            error: Some(e.to_string()),
+            placements: Vec::new(),
+            sphere_count: 0,
+            attempts: match e {
+                RetryError::Hard { attempt, .. } => attempt,
+                RetryError::TooManyAttempts { attempts, .. } => attempts,
+            },
+        },
+    }
+}
+
+/// statistics over many seeds, to compare logic changes
+#[derive(Default)]
+pub struct BatchStats {
+    pub seeds: usize,
+    pub failures: usize,
+    pub total_time: Duration,
+    // sorted, so the output is stable
+    pub item_locations: BTreeMap<(Location, Item), usize>,
+    pub sphere_counts: BTreeMap<usize, usize>,
+    /// how many seeds needed how many attempts, including failed seeds
+    pub attempt_counts: BTreeMap<u32, usize>,
+}
+
+impl BatchStats {
+    pub fn add(&mut self, result: &SeedResult) {
+        self.seeds += 1;
+        self.total_time += result.time;
+        *self.attempt_counts.entry(result.attempts).or_default() += 1;
+        if result.error.is_some() {
+            self.failures += 1;
+            return;
+        }
+        for placement in result.placements.iter() {
+            *self.item_locations.entry(*placement).or_default() += 1;
+        }
+        *self.sphere_counts.entry(result.sphere_count).or_default() += 1;
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        self.failures as f64 / self.seeds.max(1) as f64
+    }
+
+    pub fn average_time(&self) -> Duration {
+        self.total_time / self.seeds.max(1) as u32
+    }
+
+    /// writes summary.csv, item_locations.csv, sphere_counts.csv and attempt_counts.csv to the directory
+    pub fn write_csv(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("summary.csv"),
+            format!(
+                "seeds,failures,failure_rate,average_time_ms\n{},{},{},{}\n",
+                self.seeds,
+                self.failures,
+                self.failure_rate(),
+                self.average_time().as_secs_f64() * 1000.0
+            ),
+        )?;
+        let mut out = String::from("location,item,count\n");
+        for ((location, item), count) in self.item_locations.iter() {
+            writeln!(
+                &mut out,
+                "{},{},{count}",
+                csv_field(location.get().display_name),
+                csv_field(item.get().name)
+            )
+            .unwrap();
+        }
+        fs::write(dir.join("item_locations.csv"), out)?;
+        let mut out = String::from("spheres,count\n");
+        for (spheres, count) in self.sphere_counts.iter() {
+            writeln!(&mut out, "{spheres},{count}").unwrap();
+        }
+        fs::write(dir.join("sphere_counts.csv"), out)?;
+        let mut out = String::from("attempts,count\n");
+        for (attempts, count) in self.attempt_counts.iter() {
+            writeln!(&mut out, "{attempts},{count}").unwrap();
+        }
+        fs::write(dir.join("attempt_counts.csv"), out)
+    }
+
+    pub fn to_json(&self) -> String {
+        let item_locations: Vec<String> = self
+            .item_locations
+            .iter()
+            .map(|((location, item), count)| {
+                format!(
+                    "{{\"location\":{},\"item\":{},\"count\":{count}}}",
+                    json_string(location.get().display_name),
+                    json_string(item.get().name)
+                )
+            })
+            .collect();
+        let sphere_counts: Vec<String> = self
+            .sphere_counts
+            .iter()
+            .map(|(spheres, count)| format!("{{\"spheres\":{spheres},\"count\":{count}}}"))
+            .collect();
+        let attempt_counts: Vec<String> = self
+            .attempt_counts
+            .iter()
+            .map(|(attempts, count)| format!("{{\"attempts\":{attempts},\"count\":{count}}}"))
+            .collect();
+        format!(
+            "{{\"seeds\":{},\"failures\":{},\"failure_rate\":{},\"average_time_ms\":{},\"item_locations\":[{}],\"sphere_counts\":[{}],\"attempt_counts\":[{}]}}\n",
+            self.seeds,
+            self.failures,
+            self.failure_rate(),
+            self.average_time().as_secs_f64() * 1000.0,
+            item_locations.join(","),
+            sphere_counts.join(","),
+            attempt_counts.join(",")
+        )
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(&mut out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// generates every seed of the range on `threads` threads, and prints failures
+/// every seed only depends on its number, so the results don't depend on the thread count
+pub fn run_batch(
+    seeds: Range<u64>,
+    options: &Options,
+    requirements: &Requirements<'static>,
+    world_count: usize,
+    threads: usize,
+    max_attempts: u32,
+) -> BatchStats {
+    let next_seed = AtomicU64::new(seeds.start);
+    let mut results: Vec<SeedResult> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let seed = next_seed.fetch_add(1, Ordering::Relaxed);
+                        if seed >= seeds.end {
+                            return results;
+                        }
+                        results.push(generate_seed(
+                            seed,
+                            options,
+                            requirements,
+                            world_count,
+                            max_attempts,
+                        ));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    results.sort_unstable_by_key(|result| result.seed);
+
+    let mut stats = BatchStats::default();
+    for result in results.iter() {
+        if let Some(error) = &result.error {
+            eprintln!("seed {}: {error}", result.seed);
+        }
+        stats.add(result);
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::{BannedTypes, LogicMode, StartingSword};
+
+    #[test]
+    fn options_from_flags_and_files() {
+        let path = std::env::temp_dir().join("batch-options-test.txt");
+        fs::write(
+            &path,
+            "# comment\nlogic-mode: Glitched\n\nstarting-sword: Goddess Sword # the sword\n",
+        )
+        .unwrap();
+        let options = options_from_flags([
+            "--starting-sword=Master Sword",
+            format!("--options={}", path.display()).as_str(),
+            "--banned-types=combat, digging",
+            "--hero-mode=false",
+            "--random-starting-tod",
+        ])
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(options.logic_mode, LogicMode::Glitched);
+        // later flags win
+        assert_eq!(options.starting_sword, StartingSword::GoddessSword);
+        assert_eq!(
+            options.banned_types,
+            [BannedTypes::Combat, BannedTypes::Digging]
+                .into_iter()
+                .collect()
+        );
+        assert!(!options.hero_mode);
+        assert!(options.random_starting_tod);
+
+        for flag in [
+            "--unknown-option=1",
+            "--logic-mode=Nope",
+            "--required-dungeon-count=7",
+            "--banned-types=combat,nothing",
+        ] {
+            assert!(options_from_flags([flag]).is_err(), "{flag}");
+        }
+    }
+}
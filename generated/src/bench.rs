@@ -0,0 +1,114 @@
+use std::time::Instant;
+
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+
+use crate::{
+    generated::{Area, Event, Item, Options, BITLESS_BYTECODE, BITLESS_REQUIREMENTS},
+    logic_static::{check_bytecode, BitSetCompatible, Inventory, TimeOfDay},
+};
+
+/// compares checking every requirement with the tree walker and with the bytecode interpreter,
+/// on the same random inventories, and makes sure both agree
+pub fn bench_requirements(rounds: usize) {
+    let options = Options::default();
+    let mut rng = Pcg64::seed_from_u64(0);
+
+    let inventories: Vec<Inventory> = (0..rounds).map(|_| random_inventory(&mut rng)).collect();
+
+    let start = Instant::now();
+    let mut tree_results = Vec::new();
+    for inventory in inventories.iter() {
+        for tree in BITLESS_REQUIREMENTS {
+            for tod in TODS {
+                tree_results.push(tree.check(inventory, &options, tod));
+            }
+        }
+    }
+    let tree_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut bytecode_results = Vec::new();
+    for inventory in inventories.iter() {
+        for code in BITLESS_BYTECODE {
+            for tod in TODS {
+                bytecode_results.push(check_bytecode(code, inventory, &options, tod));
+            }
+        }
+    }
+    let bytecode_time = start.elapsed();
+
+    assert!(tree_results == bytecode_results);
+    let checks = tree_results.len();
+    println!("{checks} checks");
+    println!("tree:     {tree_time:?}");
+    println!("bytecode: {bytecode_time:?}");
+}
+
+const TODS: [TimeOfDay; 3] = [TimeOfDay::Day, TimeOfDay::Night, TimeOfDay::Both];
+
+/// a random part of the world, like in the middle of a fill
+fn random_inventory(rng: &mut impl Rng) -> Inventory {
+    let fraction: f64 = rng.gen();
+    let mut inventory = Inventory::default();
+    for item in Item::ALL {
+        if rng.gen_bool(fraction) {
+            inventory.insert_items(*item, rng.gen_range(1..=4));
+        }
+    }
+    for area in Area::ALL {
+        if rng.gen_bool(fraction) {
+            inventory.insert_area_tod(*area, *TODS.choose(rng).unwrap());
+        }
+    }
+    for event in Event::ALL {
+        if rng.gen_bool(fraction) {
+            inventory.insert_event(*event);
+        }
+    }
+    inventory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::{LogicMode, Trick, GLITCHED_BYTECODE, GLITCHED_REQUIREMENTS};
+
+    #[test]
+    fn bytecode_matches_tree_walker() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        for _ in 0..200 {
+            let mut random_tricks = || {
+                Trick::ALL
+                    .iter()
+                    .filter(|_| rng.gen_bool(0.5))
+                    .copied()
+                    .collect()
+            };
+            let options = Options {
+                enabled_tricks: random_tricks(),
+                enabled_tricks_glitched: random_tricks(),
+                logic_mode: *[LogicMode::BiTless, LogicMode::Glitched]
+                    .choose(&mut rng)
+                    .unwrap(),
+                ..Options::default()
+            };
+            let inventory = random_inventory(&mut rng);
+            let tables = [
+                (&BITLESS_REQUIREMENTS, &BITLESS_BYTECODE),
+                (&GLITCHED_REQUIREMENTS, &GLITCHED_BYTECODE),
+            ];
+            for (trees, codes) in tables {
+                for (key, (tree, code)) in trees.iter().zip(codes.iter()).enumerate() {
+                    for tod in TODS {
+                        assert_eq!(
+                            tree.check(&inventory, &options, tod),
+                            check_bytecode(code, &inventory, &options, tod),
+                            "requirement {key} at {tod:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::generated::{Event, Item, Location, Region, Stage};
+use crate::generated::{BannedTypes, Event, Exit, Item, JunkDistribution, Location, Region, Stage};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Dungeon {
@@ -42,8 +42,57 @@ impl Dungeon {
             Dungeon::FireSanctuary => Region::FireSanctuary,
         }
     }
+
+    /// the exit from the overworld into the dungeon
+    pub fn get_entry_exit(&self) -> Exit {
+        match self {
+            Dungeon::Skyivew => Exit::DeepWoods_To_SkyviewTemple,
+            Dungeon::EarthTemple => Exit::EldinVolcano_To_EarthTemple,
+            Dungeon::LanayruMiningFacility => Exit::LanayruDesert_To_LanayruMiningFacilityA,
+            Dungeon::AncientCistern => Exit::FloriaWaterfall_To_AncientCistern,
+            Dungeon::Sandship => Exit::SandSea_To_Sandship,
+            Dungeon::FireSanctuary => Exit::OutsideFireSanctuary_To_FireSanctuaryA,
+        }
+    }
 }
 
+// regions to start in with a random starting entrance
+pub const SKYLOFT_REGIONS: &[Region] = &[
+    Region::CentralSkyloft,
+    Region::SkyloftVillage,
+    Region::KnightAcademy,
+    Region::Batreaux,
+];
+
+pub const SURFACE_REGIONS: &[Region] = &[
+    Region::FaronWoods,
+    Region::LakeFloria,
+    Region::SealedGrounds,
+    Region::EldinVolcano,
+    Region::VolcanoSummit,
+    Region::MogmaTurf,
+    Region::LanayruDesert,
+    Region::LanayruMines,
+    Region::LanayruCaves,
+    Region::LanayruSandSea,
+];
+
+pub const SKY_REGIONS: &[Region] = &[Region::Sky, Region::Thunderhead, Region::Beedle];
+
+// banned types that name where a check is, they don't apply to dungeon checks,
+// since the dungeon item settings keep keys inside of their dungeon
+pub const REGION_TYPES: &[BannedTypes] = &[
+    BannedTypes::Skyloft,
+    BannedTypes::Sky,
+    BannedTypes::Thunderhead,
+    BannedTypes::Faron,
+    BannedTypes::Eldin,
+    BannedTypes::Lanayru,
+];
+
+// sky keep isn't a `Dungeon`, but can be shuffled with them
+pub const SKY_KEEP_ENTRY_EXIT: Exit = Exit::Skyloft_To_SkyKeepEntry;
+
 pub struct DungeonRestrictionInfo {
     pub map: (Item, Location),
     pub small_key: Option<(Item, &'static [Location])>,
@@ -241,6 +290,24 @@ pub const NONPROGRESS_ITEMS: &[(Item, u8)] = &[
     (Item::SkyKeepMap, 1),
 ];
 
+// (item, weight) of the junk added to the pool when there are more locations than items
+pub fn junk_distribution(distribution: JunkDistribution) -> &'static [(Item, u32)] {
+    match distribution {
+        JunkDistribution::Rupees => &[
+            (Item::RedRupee, 8),
+            (Item::BlueRupee, 2),
+            (Item::SilverRupee, 1),
+        ],
+        JunkDistribution::RedRupees => &[(Item::RedRupee, 1)],
+        JunkDistribution::Treasures => &[
+            (Item::SemiRareTreasure, 4),
+            (Item::RareTreasure, 2),
+            (Item::EldinOre, 1),
+            (Item::Tumbleweed, 1),
+        ],
+    }
+}
+
 pub const CONSUMABLE_ITEMS: &[(Item, u8)] = &[
     (Item::GreenRupee, 3),
     (Item::BlueRupee, 11),
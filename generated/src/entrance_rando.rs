@@ -0,0 +1,361 @@
+use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use rand::prelude::*;
+
+use crate::{
+    constants::{Dungeon, SKYLOFT_REGIONS, SKY_KEEP_ENTRY_EXIT, SKY_REGIONS, SURFACE_REGIONS},
+    explorer::{MultiworldExplorer, Placement},
+    generated::{
+        Area, ConnectionShuffleType, DoorConnection, Entrance, Exit, Item, Options,
+        RandomStartingEntrance, RandomizeEntrances, Region, ShuffleAllEntrances,
+    },
+    logic_static::{BitSetCompatible, Requirements, TimeOfDay},
+};
+
+/// the exit that leads out of the entrance, back to where the entrance is entered from
+pub fn get_return_exit(entrance: Entrance) -> Option<Exit> {
+    Exit::ALL
+        .iter()
+        .find(|exit| exit.get().coupled_entrance == Some(entrance))
+        .copied()
+}
+
+/// shuffles which dungeon the dungeon entry exits lead to, according to `randomize_entrances`
+/// the entrances stay coupled, leaving a dungeon leads back to where it was entered from
+/// returns the shuffled exits, including the ones out of the dungeons
+pub fn randomize_dungeon_entrances<R: Rng>(
+    rng: &mut R,
+    placement: &mut Placement,
+    options: &Options,
+    required_dungeons: &[&Dungeon],
+) -> Vec<Exit> {
+    let entry_exits = |required: Option<bool>| -> Vec<Exit> {
+        Dungeon::POTENTIALLY_REQUIRED
+            .iter()
+            .filter(|dungeon| required.is_none_or(|r| required_dungeons.contains(dungeon) == r))
+            .map(|dungeon| dungeon.get_entry_exit())
+            .collect()
+    };
+    // dungeons are only shuffled within their group
+    let mut groups: Vec<Vec<Exit>> = match options.randomize_entrances {
+        RandomizeEntrances::None => return Vec::new(),
+        RandomizeEntrances::RequiredDungeonsSeparately => {
+            vec![entry_exits(Some(true)), entry_exits(Some(false))]
+        }
+        RandomizeEntrances::AllDungeons | RandomizeEntrances::AllDungeonsSkyKeep => {
+            vec![entry_exits(None)]
+        }
+    };
+    // sky keep is only shuffled when it isn't skipped, with the required dungeons
+    if !options.skip_skykeep
+        && matches!(
+            options.randomize_entrances,
+            RandomizeEntrances::RequiredDungeonsSeparately | RandomizeEntrances::AllDungeonsSkyKeep
+        )
+    {
+        groups[0].push(SKY_KEEP_ENTRY_EXIT);
+    }
+    let mut shuffled = Vec::new();
+    for entries in groups {
+        let mut dungeon_entrances: Vec<Entrance> = entries
+            .iter()
+            .map(|entry| entry.get().vanilla_entrance.unwrap())
+            .collect();
+        shuffled.extend(entries.iter().copied());
+        shuffled.extend(
+            dungeon_entrances
+                .iter()
+                .map(|entrance| get_return_exit(*entrance).unwrap()),
+        );
+        dungeon_entrances.shuffle(rng);
+        connect_coupled(placement, &entries, &dungeon_entrances);
+    }
+    shuffled
+}
+
+/// connects every exit to its entrance, and the exit back out of the entrance to where the exit comes from
+/// all exits involved are disconnected first, so they can be connected in any order
+pub fn connect_coupled(placement: &mut Placement, exits: &[Exit], entrances: &[Entrance]) {
+    let return_exits: Vec<Exit> = entrances
+        .iter()
+        .map(|entrance| get_return_exit(*entrance).unwrap())
+        .collect();
+    for exit in exits.iter().chain(return_exits.iter()) {
+        placement.disconnect_exit(*exit);
+    }
+    for ((exit, entrance), return_exit) in exits.iter().zip(entrances).zip(return_exits) {
+        placement.connect(*entrance, *exit);
+        if let Some(coupled_entrance) = exit.get().coupled_entrance {
+            placement.connect(coupled_entrance, return_exit);
+        }
+    }
+}
+
+/// the exits through the trial gates, into the silent realms, as marked in the entrance table
+pub fn trial_gate_exits() -> Vec<Exit> {
+    Exit::ALL
+        .iter()
+        .filter(|exit| exit.get().connection_shuffle_type == ConnectionShuffleType::TrialGate)
+        .copied()
+        .collect()
+}
+
+/// shuffles which silent realm every trial gate leads to, if `randomize_trials` is set
+/// leaving a silent realm leads back to the gate it was entered from, its rewards stay in the realm
+pub fn randomize_trial_gates<R: Rng>(rng: &mut R, placement: &mut Placement, options: &Options) {
+    if !options.randomize_trials {
+        return;
+    }
+    let gates = trial_gate_exits();
+    let mut trials: Vec<Entrance> = gates
+        .iter()
+        .map(|gate| gate.get().vanilla_entrance.unwrap())
+        .collect();
+    trials.shuffle(rng);
+    connect_coupled(placement, &gates, &trials);
+}
+
+/// one side of a door, the left half of a double door comes first
+type DoorSide = Vec<Exit>;
+
+/// the door sides that can be shuffled and aren't shuffled already, with the index of the
+/// side on the other end of their door
+fn shuffleable_door_sides(shuffled: &HashSet<Exit>) -> Vec<(DoorSide, usize)> {
+    let is_shuffleable = |exit: Exit| {
+        let exit_def = exit.get();
+        exit_def.connection_shuffle_type == ConnectionShuffleType::Other
+            && exit_def.coupled_entrance.is_some()
+            && exit_def.vanilla_entrance.is_some()
+            && !shuffled.contains(&exit)
+    };
+    let sides: Vec<DoorSide> = Exit::ALL
+        .iter()
+        .filter(|exit| is_shuffleable(**exit))
+        .filter_map(|exit| match exit.get().door_connection {
+            DoorConnection::No => Some(vec![*exit]),
+            DoorConnection::Right(right) => is_shuffleable(right).then(|| vec![*exit, right]),
+            // part of the side of its left half
+            DoorConnection::Left(_) => None,
+        })
+        .collect();
+    let other_side = |sides: &[DoorSide], side: &DoorSide| {
+        let return_exit = get_return_exit(side[0].get().vanilla_entrance?)?;
+        sides.iter().position(|side| side.contains(&return_exit))
+    };
+    // both sides of a door have to be shuffleable
+    let sides: Vec<DoorSide> = sides
+        .iter()
+        .enumerate()
+        .filter(|(index, side)| {
+            other_side(&sides, side)
+                .is_some_and(|other| other_side(&sides, &sides[other]) == Some(*index))
+        })
+        .map(|(_, side)| side.clone())
+        .collect();
+    sides
+        .iter()
+        .map(|side| (side.clone(), other_side(&sides, side).unwrap()))
+        .collect()
+}
+
+/// the areas that can be reached from the area over connected exits, ignoring requirements
+fn reachable_areas(placement: &Placement, area: Area) -> HashSet<Area> {
+    let mut reachable = HashSet::default();
+    let mut todo = vec![area];
+    while let Some(area) = todo.pop() {
+        if !reachable.insert(area) {
+            continue;
+        }
+        let area_def = area.get();
+        todo.extend(area_def.logic_exits.iter().copied());
+        todo.extend(
+            area_def
+                .map_exits
+                .iter()
+                .filter_map(|exit| placement.get_connected_entrance(*exit))
+                .map(|entrance| entrance.get().area),
+        );
+    }
+    reachable
+}
+
+/// the vanilla entrances of the exits of a door side
+fn vanilla_entrances(side: &[Exit]) -> Vec<Entrance> {
+    side.iter()
+        .map(|exit| exit.get().vanilla_entrance.unwrap())
+        .collect()
+}
+
+/// connects the exits of a door side, both halves of a double door lead to the same place:
+/// if there are fewer entrances than exits, all exits lead to the last entrance
+fn connect_side(placement: &mut Placement, side: &[Exit], entrances: &[Entrance]) {
+    for (index, exit) in side.iter().enumerate() {
+        placement.disconnect_exit(*exit);
+        placement.connect(entrances[index.min(entrances.len() - 1)], *exit);
+    }
+}
+
+/// the door sides of `candidates` that can be gone through from the start, with the items
+fn usable_sides(
+    placement: &Placement,
+    requirements: &Requirements<'_>,
+    options: &Options,
+    items: &HashMap<Item, u8>,
+    sides: &[(DoorSide, usize)],
+    candidates: &[usize],
+) -> Vec<usize> {
+    let mut explorer = MultiworldExplorer::new();
+    explorer.add_world(0, None, placement, requirements, options);
+    for (item, count) in items.iter() {
+        explorer.insert_items(0, *item, *count);
+    }
+    candidates
+        .iter()
+        .copied()
+        .filter(|index| explorer.can_reach(0, sides[*index].0[0]))
+        .collect()
+}
+
+/// shuffles every entrance that can be shuffled and isn't in `shuffled`, according to `shuffle_all_entrances`
+/// the start has to be picked already, the coupled shuffle grows outwards from it,
+/// following the logic with all `items`
+pub fn randomize_all_entrances<R: Rng>(
+    rng: &mut R,
+    placement: &mut Placement,
+    options: &Options,
+    shuffled: &HashSet<Exit>,
+    requirements: &Requirements<'_>,
+    items: &HashMap<Item, u8>,
+) {
+    let sides = shuffleable_door_sides(shuffled);
+    // where the exits of a side lead to is what the exits on the other end of its door lead to
+    let arrivals: Vec<Vec<Entrance>> = sides
+        .iter()
+        .map(|(side, _)| vanilla_entrances(side))
+        .collect();
+    match options.shuffle_all_entrances {
+        ShuffleAllEntrances::Off => (),
+        ShuffleAllEntrances::Coupled => {
+            // every door side is paired with another, so going through a door and back leads to where it started
+            // pairing randomly mostly joins rooms with only one door to each other, cut off from everything else,
+            // so sides that can already be gone through are paired with sides that can't, as long as there are some
+            // only the doors decide what is connected, but the logic decides which sides can be gone through,
+            // so one-way drops and locked doors don't count as a way on
+            for (side, _) in sides.iter() {
+                for exit in side.iter() {
+                    placement.disconnect_exit(*exit);
+                }
+            }
+            let side_area = |index: usize| sides[index].0[0].get().area;
+            let mut unpaired: Vec<usize> = (0..sides.len()).collect();
+            let no_items = HashMap::default();
+            let mut without_items = true;
+            while !unpaired.is_empty() {
+                let usable =
+                    usable_sides(placement, requirements, options, items, &sides, &unpaired);
+                let (open, unreached): (Vec<usize>, Vec<usize>) =
+                    unpaired.iter().partition(|index| usable.contains(index));
+                // the start grows with the sides that need no items first, so there is something to do there
+                let mut first_choices = Vec::new();
+                if without_items {
+                    first_choices =
+                        usable_sides(placement, requirements, options, &no_items, &sides, &open);
+                    without_items = !first_choices.is_empty();
+                }
+                if first_choices.is_empty() {
+                    first_choices = open.clone();
+                }
+                let first = *first_choices
+                    .choose(rng)
+                    .unwrap_or_else(|| unreached.choose(rng).unwrap());
+                unpaired.retain(|index| *index != first);
+                let mut candidates: Vec<usize> = unreached
+                    .iter()
+                    .copied()
+                    .filter(|index| *index != first)
+                    .collect();
+                // pairing the last open side with a room without other doors closes off everything else
+                if open.len() == 1 {
+                    let has_other_door = |index: usize| {
+                        let areas = reachable_areas(placement, side_area(index));
+                        unpaired
+                            .iter()
+                            .any(|other| *other != index && areas.contains(&side_area(*other)))
+                    };
+                    let with_other_doors: Vec<usize> = candidates
+                        .iter()
+                        .copied()
+                        .filter(|index| has_other_door(*index))
+                        .collect();
+                    if !with_other_doors.is_empty() {
+                        candidates = with_other_doors;
+                    }
+                }
+                if candidates.is_empty() {
+                    candidates = unpaired.clone();
+                }
+                let Some(&second) = candidates.choose(rng) else {
+                    // there is an odd number of sides, this one stays vanilla
+                    connect_side(placement, &sides[first].0, &arrivals[first]);
+                    break;
+                };
+                unpaired.retain(|index| *index != second);
+                connect_side(placement, &sides[first].0, &arrivals[sides[second].1]);
+                connect_side(placement, &sides[second].0, &arrivals[sides[first].1]);
+            }
+        }
+        ShuffleAllEntrances::Decoupled => {
+            let mut arrivals = arrivals;
+            arrivals.shuffle(rng);
+            for ((side, _), entrances) in sides.iter().zip(arrivals) {
+                connect_side(placement, side, &entrances);
+            }
+        }
+    }
+}
+
+/// picks the starting entrance and time of day, according to `random_starting_entrance`
+/// and `random_starting_tod`, if the area forces a time of day, that one is used
+pub fn randomize_start<R: Rng>(rng: &mut R, placement: &mut Placement, options: &Options) {
+    let regions: Vec<Region> = match options.random_starting_entrance {
+        RandomStartingEntrance::Vanilla => Vec::new(),
+        RandomStartingEntrance::AnySkyloft => SKYLOFT_REGIONS.to_vec(),
+        RandomStartingEntrance::AnySurface => SURFACE_REGIONS.to_vec(),
+        RandomStartingEntrance::Any => [SKYLOFT_REGIONS, SURFACE_REGIONS, SKY_REGIONS].concat(),
+    };
+    if !regions.is_empty() {
+        // the game can only spawn at entrances it knows the stage info of
+        let entrances: Vec<Entrance> = Entrance::ALL
+            .iter()
+            .filter(|entrance| {
+                let entrance_def = entrance.get();
+                entrance_def.patch_info.is_some()
+                    && regions.contains(&entrance_def.area.get().region)
+            })
+            .copied()
+            .collect();
+        if let Some(entrance) = entrances.choose(rng) {
+            placement.initial_entrance = Some((*entrance, TimeOfDay::Day));
+        }
+    }
+    if let Some((entrance, tod)) = placement.initial_entrance.as_mut() {
+        if options.random_starting_tod {
+            *tod = *[TimeOfDay::Day, TimeOfDay::Night].choose(rng).unwrap();
+        }
+        let possible_tod = TimeOfDay::from_force_tod(entrance.get().area.get().time_of_day);
+        if !possible_tod.contains(*tod) {
+            *tod = possible_tod;
+        }
+    }
+}
+
+/// the connections that differ from vanilla, to write them to the spoiler
+pub fn shuffled_connections(placement: &Placement) -> Vec<(Exit, Entrance)> {
+    let mut connections: Vec<(Exit, Entrance)> = placement
+        .connections
+        .iter()
+        .filter(|(exit, entrance)| exit.get().vanilla_entrance != Some(**entrance))
+        .map(|(exit, entrance)| (*exit, *entrance))
+        .collect();
+    connections.sort_unstable();
+    connections
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::{
+    generated::{Area, Event, Item, Options, Trick},
+    logic_static::{Inventory, RequirementExpression, RequirementKey, Requirements, TimeOfDay},
+};
+
+/// something a requirement needs that the inventory doesn't have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Missing {
+    /// the item, with how many of it are needed in total
+    Item(Item, u8),
+    Event(Event),
+    Area(Area, TimeOfDay),
+    Trick(Trick),
+    /// an option has the wrong value
+    Option,
+    /// something is collected that must not be
+    Negation,
+    /// can't be met at all
+    Impossible,
+}
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Missing::Item(item, 1) => write!(f, "{}", item.get().name),
+            Missing::Item(item, count) => write!(f, "{count}x {}", item.get().name),
+            Missing::Event(event) => write!(f, "event {}", event.get().name),
+            Missing::Area(area, tod) => {
+                write!(f, "area {}", area.get().full_name)?;
+                if *tod == TimeOfDay::Day {
+                    write!(f, " at day")?;
+                } else if *tod == TimeOfDay::Night {
+                    write!(f, " at night")?;
+                }
+                Ok(())
+            }
+            Missing::Trick(trick) => write!(f, "trick {}", trick.get().name),
+            Missing::Option => write!(f, "different options"),
+            Missing::Negation => write!(f, "not having something"),
+            Missing::Impossible => write!(f, "impossible"),
+        }
+    }
+}
+
+/// adds to a list of missing things, only keeping the highest item count and combining times of day
+fn add_missing(missing: &mut Vec<Missing>, new: Missing) {
+    for existing in missing.iter_mut() {
+        match (existing, new) {
+            (Missing::Item(item, count), Missing::Item(new_item, new_count))
+                if *item == new_item =>
+            {
+                *count = (*count).max(new_count);
+                return;
+            }
+            (Missing::Area(area, tod), Missing::Area(new_area, new_tod)) if *area == new_area => {
+                *tod |= new_tod;
+                return;
+            }
+            (existing, new) if *existing == new => return,
+            _ => (),
+        }
+    }
+    missing.push(new);
+}
+
+/// everything a requirement still needs, for `Or` only the option that needs the least
+/// empty if the requirement is already met
+pub fn explain_expression(
+    expr: &RequirementExpression<'_>,
+    inventory: &Inventory,
+    options: &Options,
+    allowed_tod: TimeOfDay,
+) -> Vec<Missing> {
+    if expr.check(inventory, options, allowed_tod) {
+        return Vec::new();
+    }
+    match expr {
+        RequirementExpression::And(exprs) => {
+            let mut missing = Vec::new();
+            for expr in exprs.iter() {
+                for new in explain_expression(expr, inventory, options, allowed_tod) {
+                    add_missing(&mut missing, new);
+                }
+            }
+            missing
+        }
+        RequirementExpression::Or(exprs) => exprs
+            .iter()
+            .map(|expr| explain_expression(expr, inventory, options, allowed_tod))
+            .min_by_key(|missing| missing.len())
+            .unwrap_or_else(|| vec![Missing::Impossible]),
+        RequirementExpression::Item(item, count) => vec![Missing::Item(*item, *count)],
+        RequirementExpression::Event(event) => vec![Missing::Event(*event)],
+        RequirementExpression::Area(area, tod) => {
+            let tod = tod.intersection(allowed_tod);
+            if tod.is_empty() {
+                vec![Missing::Impossible]
+            } else {
+                vec![Missing::Area(*area, tod)]
+            }
+        }
+        RequirementExpression::Fixed(_) => vec![Missing::Impossible],
+        RequirementExpression::Trick(trick) => vec![Missing::Trick(*trick)],
+        RequirementExpression::Not(_) => vec![Missing::Negation],
+        RequirementExpression::Option(_) => vec![Missing::Option],
+        RequirementExpression::Ref(expr) => {
+            explain_expression(expr, inventory, options, allowed_tod)
+        }
+    }
+}
+
+/// why a requirement can't be met with the inventory, see `explain_expression`
+pub fn explain_requirement(
+    requirements: &Requirements<'_>,
+    requirement: RequirementKey,
+    inventory: &Inventory,
+    options: &Options,
+) -> Vec<Missing> {
+    match requirements.get_requirement(requirement) {
+        Some(expr) => explain_expression(expr, inventory, options, TimeOfDay::all()),
+        None => vec![Missing::Impossible],
+    }
+}
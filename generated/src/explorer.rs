@@ -1,10 +1,11 @@
 use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
 
 use crate::{
-    generated::{Area, Entrance, Event, Exit, Item, Location, Options},
+    explain::{explain_requirement, Missing},
+    generated::{Area, Entrance, Exit, Item, Location, Options},
     logic_static::{
-        BitSetCompatible, EventBitset, Inventory, LocationBitset, RequirementKey, Requirements,
-        TimeOfDay,
+        BitSetCompatible, Dependency, EventBitset, Inventory, LocationBitset, RequirementKey,
+        RequirementKeyBitset, Requirements, TimeOfDay,
     },
     plando::ItemOrVacant,
 };
@@ -253,18 +254,213 @@ impl SingleWorldPlacement {
 //     }
 // }
 
-struct SingleWorld<'a> {
+/// the exploration progress of one world
+/// instead of checking every requirement until nothing changes, only the requirements that mention
+/// something new are checked again
+#[derive(Clone)]
+struct WorldState {
     inventory: Inventory,
-    has_updated_items: bool,
     collected_locations: LocationBitset,
+    // requirements that have to be checked (again), `queued` avoids duplicates in the worklist
+    worklist: Vec<RequirementKey>,
+    queued: RequirementKeyBitset,
+}
+
+impl WorldState {
+    fn new(placement: &Placement) -> Self {
+        let mut inventory = placement.get_initial_inventory();
+        for area in Area::ALL {
+            if area.get().can_sleep && !inventory.get_area_tod(*area).is_empty() {
+                inventory.insert_area_tod(*area, TimeOfDay::all());
+            }
+        }
+        // everything has to be checked at least once
+        WorldState {
+            inventory,
+            collected_locations: LocationBitset::new(),
+            worklist: RequirementKey::ALL.to_vec(),
+            queued: RequirementKeyBitset::new_all_set(),
+        }
+    }
+
+    fn queue(&mut self, requirement: RequirementKey) {
+        if !self.queued.has(requirement) {
+            self.queued.insert(requirement);
+            self.worklist.push(requirement);
+        }
+    }
+
+    fn queue_dependents(&mut self, requirements: &Requirements<'_>, dependency: Dependency) {
+        requirements.for_each_dependent(dependency, &mut |requirement| self.queue(requirement));
+    }
+
+    fn insert_items(&mut self, requirements: &Requirements<'_>, item: Item, count: u8) {
+        if count > 0 {
+            self.inventory.insert_items(item, count);
+            self.queue_dependents(requirements, Dependency::Item(item));
+        }
+    }
+
+    fn insert_area_tod(&mut self, requirements: &Requirements<'_>, area: Area, tod: TimeOfDay) {
+        let already_reached_tod = self.inventory.get_area_tod(area);
+        // sleeping makes both times of day reachable
+        let tod = if area.get().can_sleep && !tod.is_empty() {
+            TimeOfDay::all()
+        } else {
+            tod
+        };
+        if !already_reached_tod.contains(tod) {
+            self.inventory.insert_area_tod(area, tod);
+            self.queue_dependents(requirements, Dependency::Area(area));
+        }
+    }
+
+    /// enters an area by an exit or a logic exit with the requirement
+    fn try_enter(
+        &mut self,
+        world: &ExploredWorld<'_>,
+        area: Area,
+        requirement: RequirementKey,
+        force_tod: bool,
+    ) {
+        if world
+            .banned_areas
+            .is_some_and(|banned| banned.contains(&area))
+        {
+            return;
+        }
+        let already_reached_tod = self.inventory.get_area_tod(area);
+        let possible_tod = TimeOfDay::from_force_tod(area.get().time_of_day);
+        if already_reached_tod.contains(possible_tod) {
+            // this area is already fully accessible
+            return;
+        }
+        if force_tod && !possible_tod.is_all() {
+            // the ToD the exit is taken at doesn't matter
+            if world.check(requirement, &self.inventory, TimeOfDay::all()) {
+                self.insert_area_tod(world.requirements, area, possible_tod);
+            }
+        } else {
+            // check for both day and night if the exit can be taken
+            for tod in [TimeOfDay::Day, TimeOfDay::Night] {
+                if !already_reached_tod.contains(tod)
+                    && world.check(requirement, &self.inventory, tod)
+                {
+                    self.insert_area_tod(world.requirements, area, tod);
+                }
+            }
+        }
+    }
+
+    /// checks queued requirements until there is nothing left to check,
+    /// newly reachable locations are added to `found` with the world and item placed there
+    fn explore(&mut self, world: &ExploredWorld<'_>, found: &mut Vec<(Location, usize, Item)>) {
+        while let Some(requirement) = self.worklist.pop() {
+            self.queued.remove(requirement);
+            match requirement {
+                RequirementKey::Exit(exit) => {
+                    if let Some(entrance) = world.placement.get_connected_entrance(exit) {
+                        self.try_enter(world, entrance.get().area, requirement, true);
+                    }
+                }
+                RequirementKey::LogicExit { to, .. } => {
+                    self.try_enter(world, to, requirement, false);
+                }
+                RequirementKey::Event(event) => {
+                    if !self.inventory.has_event(event)
+                        && world.check(requirement, &self.inventory, TimeOfDay::all())
+                    {
+                        self.inventory.insert_event(event);
+                        self.queue_dependents(world.requirements, Dependency::Event(event));
+                    }
+                }
+                RequirementKey::Location(location) => {
+                    if self.collected_locations.has(location) {
+                        continue;
+                    }
+                    // locations without an item are checked again once something is placed there
+                    let Some((item_world, item)) = world.get_item_at(location) else {
+                        continue;
+                    };
+                    if world.check(requirement, &self.inventory, TimeOfDay::all()) {
+                        self.collected_locations.insert(location);
+                        if let ItemOrVacant::Item(item) = item {
+                            found.push((location, item_world, item));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// queues the locations that got an item since this state was saved
+    fn queue_placed_locations(&mut self, world: &ExploredWorld<'_>) {
+        let placed = world.placed.into_iter().flat_map(|placed| placed.keys());
+        for location in world.placement.locations.keys().chain(placed) {
+            if !self.collected_locations.has(*location) {
+                self.queue((*location).into());
+            }
+        }
+    }
+}
+
+/// what doesn't change while exploring a world
+struct ExploredWorld<'a> {
+    banned_areas: Option<&'a HashSet<Area>>,
+    placement: &'a Placement,
+    // items placed through the explorer, that aren't in the placement yet
+    placed: Option<&'a HashMap<Location, (usize, ItemOrVacant)>>,
+    requirements: &'a Requirements<'a>,
+    options: &'a Options,
+}
+
+impl<'a> ExploredWorld<'a> {
+    fn get_item_at(&self, location: Location) -> Option<(usize, ItemOrVacant)> {
+        self.placed
+            .and_then(|placed| placed.get(&location).copied())
+            .or_else(|| self.placement.get_item_at(location))
+    }
+
+    fn check(&self, requirement: RequirementKey, inventory: &Inventory, tod: TimeOfDay) -> bool {
+        self.requirements
+            .check(requirement, inventory, self.options, tod)
+    }
+}
+
+struct SingleWorld<'a> {
+    state: WorldState,
     banned_areas: Option<&'a HashSet<Area>>,
-    placement: &'a mut Placement,
+    placement: &'a Placement,
+    placed: HashMap<Location, (usize, ItemOrVacant)>,
     requirements: &'a Requirements<'a>,
     options: &'a Options,
 }
 
+impl<'a> SingleWorld<'a> {
+    fn split(&mut self) -> (&mut WorldState, ExploredWorld<'_>) {
+        let world = ExploredWorld {
+            banned_areas: self.banned_areas,
+            placement: self.placement,
+            placed: Some(&self.placed),
+            requirements: self.requirements,
+            options: self.options,
+        };
+        (&mut self.state, world)
+    }
+
+    fn explore(&mut self, found: &mut Vec<(Location, usize, Item)>) {
+        let (state, world) = self.split();
+        state.explore(&world, found);
+    }
+}
+
+/// the state of all worlds of a `MultiworldExplorer`, to go back to later
+#[derive(Clone)]
+pub struct ExplorerSnapshot {
+    states: Vec<(usize, WorldState)>,
+}
+
 pub struct MultiworldExplorer<'a> {
-    needs_reset: bool,
     worlds: HashMap<usize, SingleWorld<'a>>,
 }
 
@@ -277,7 +473,6 @@ impl<'a> Default for MultiworldExplorer<'a> {
 impl<'a> MultiworldExplorer<'a> {
     pub fn new() -> Self {
         MultiworldExplorer {
-            needs_reset: false,
             worlds: Default::default(),
         }
     }
@@ -286,7 +481,7 @@ impl<'a> MultiworldExplorer<'a> {
         &mut self,
         world: usize,
         banned_areas: Option<&'a HashSet<Area>>,
-        placement: &'a mut Placement,
+        placement: &'a Placement,
         requirements: &'a Requirements<'a>,
         options: &'a Options,
     ) {
@@ -295,11 +490,10 @@ impl<'a> MultiworldExplorer<'a> {
             .insert(
                 world,
                 SingleWorld {
-                    inventory: placement.get_initial_inventory(),
-                    has_updated_items: true,
-                    collected_locations: LocationBitset::new(),
+                    state: WorldState::new(placement),
                     options,
                     placement,
+                    placed: Default::default(),
                     banned_areas,
                     requirements,
                 },
@@ -316,275 +510,335 @@ impl<'a> MultiworldExplorer<'a> {
 
     // TODO catch index OoB?
     pub fn insert_item(&mut self, world: usize, item: Item) {
-        self.get_world_mut(world).inventory.insert_item(item);
+        self.insert_items(world, item, 1);
     }
 
     pub fn insert_items(&mut self, world: usize, item: Item, count: u8) {
-        self.get_world_mut(world)
-            .inventory
-            .insert_items(item, count);
+        let world = self.get_world_mut(world);
+        world.state.insert_items(world.requirements, item, count);
     }
 
     pub fn insert_area_tod(&mut self, world: usize, area: Area, tod: TimeOfDay) {
-        self.get_world_mut(world)
-            .inventory
-            .insert_area_tod(area, tod);
+        let world = self.get_world_mut(world);
+        world.state.insert_area_tod(world.requirements, area, tod);
     }
 
-    pub fn can_reach(&mut self, target_world: usize, req: impl Into<RequirementKey>) -> bool {
-        // this feels correct but suboptimal
-        // first, check if the requirement is already met
-        let requirement = req.into();
-        if self.check_req(target_world, requirement) {
-            return true;
+    /// places an item for exploring, the placement is shared, so it has to be added there
+    /// afterwards, see `take_placed`
+    pub fn set_location(
+        &mut self,
+        world: usize,
+        location: Location,
+        item_world: usize,
+        item: ItemOrVacant,
+    ) {
+        let world = self.get_world_mut(world);
+        let prev = world.placed.insert(location, (item_world, item));
+        assert!(prev.is_none() && world.placement.get_item_at(location).is_none());
+        world.state.queue(location.into());
+    }
+
+    /// the items placed with `set_location`, as (world, location, item world, item)
+    pub fn take_placed(&mut self) -> Vec<(usize, Location, usize, ItemOrVacant)> {
+        let mut placed = Vec::new();
+        for (world_id, world) in self.worlds.iter_mut() {
+            placed.extend(
+                world
+                    .placed
+                    .drain()
+                    .map(|(location, (item_world, item))| (*world_id, location, item_world, item)),
+            );
         }
-        for world in self.worlds.values_mut() {
-            world.has_updated_items = true;
+        placed
+    }
+
+    pub fn snapshot(&self) -> ExplorerSnapshot {
+        ExplorerSnapshot {
+            states: self
+                .worlds
+                .iter()
+                .map(|(world_id, world)| (*world_id, world.state.clone()))
+                .collect(),
         }
-        // otherwise explore and find items in every world
-        let world_ids: Vec<usize> = self.worlds.keys().cloned().collect();
+    }
+
+    /// goes back to the state of the snapshot, items placed since then are kept
+    pub fn restore(&mut self, snapshot: &ExplorerSnapshot) {
+        for (world_id, state) in snapshot.states.iter() {
+            let (world_state, world) = self.get_world_mut(*world_id).split();
+            world_state.clone_from(state);
+            world_state.queue_placed_locations(&world);
+        }
+    }
+
+    /// explores every world until nothing new can be found,
+    /// returns if anything was found
+    pub fn explore(&mut self) -> bool {
+        let mut world_ids: Vec<usize> = self.worlds.keys().copied().collect();
+        world_ids.sort_unstable();
+        let mut found = Vec::new();
+        let mut any_found = false;
         loop {
-            let mut any_update = false;
-            for world_id in &world_ids {
-                let world = self.get_world_mut(*world_id);
-                if !world.has_updated_items {
-                    continue;
-                }
-                world.has_updated_items = false;
-                while explore_areas(
-                    world.requirements,
-                    world.placement,
-                    world.options,
-                    &mut world.inventory,
-                    world.banned_areas,
-                    &HashMap::default(),
-                ) {
-                    any_update = true;
-                }
-                while collect_events(world.requirements, world.options, &mut world.inventory) {
-                    world.has_updated_items = true;
-                    any_update = true;
-                }
-                for loc in Location::ALL.iter() {
-                    let world = self.get_world_mut(*world_id);
-                    if !world.collected_locations.has(*loc)
-                        && world.requirements.check(
-                            loc.into(),
-                            &world.inventory,
-                            world.options,
-                            TimeOfDay::all(),
-                        )
-                    {
-                        world.collected_locations.insert(*loc);
-                        if let Some((item_world, item)) =
-                            world.placement.locations.get(loc).copied()
-                        {
-                            if let ItemOrVacant::Item(item) = item {
-                                self.get_world_mut(item_world).inventory.insert_item(item);
-                                self.get_world_mut(item_world).has_updated_items = true;
-                                any_update = true;
-                            }
-                        }
-                    }
+            let mut found_items = false;
+            for world_id in world_ids.iter() {
+                self.get_world_mut(*world_id).explore(&mut found);
+                for (_, item_world, item) in found.drain(..) {
+                    self.insert_item(item_world, item);
+                    found_items = true;
                 }
             }
-            if self.check_req(target_world, requirement) {
-                return true;
-            }
-            if !any_update {
-                break;
+            // without new items, every world is fully explored
+            if !found_items {
+                return any_found;
             }
+            any_found = true;
         }
-        false
+    }
+
+    pub fn can_reach(&mut self, target_world: usize, req: impl Into<RequirementKey>) -> bool {
+        let requirement = req.into();
+        self.check_req(target_world, requirement) || {
+            self.explore();
+            self.check_req(target_world, requirement)
+        }
+    }
+
+    /// what is missing to meet the requirement with everything found so far,
+    /// only complete after `can_reach` failed for it
+    pub fn explain(&self, world: usize, req: impl Into<RequirementKey>) -> Vec<Missing> {
+        let w = &self.worlds[&world];
+        explain_requirement(w.requirements, req.into(), &w.state.inventory, w.options)
     }
 
     fn check_req(&self, world: usize, req: RequirementKey) -> bool {
         let w = &self.worlds[&world];
         w.requirements
-            .check(req, &w.inventory, w.options, TimeOfDay::all())
+            .check(req, &w.state.inventory, w.options, TimeOfDay::all())
     }
-
-    pub fn explore_areas(&mut self) {}
 }
 
-#[derive(Clone)]
+/// explores a finished placement sphere by sphere, every sphere contains the locations that can be
+/// reached with the items of all previous spheres
+/// items can be placed for other worlds, so all worlds are explored at the same time
 pub struct SphereExplorer<'a> {
-    pub inventory: Inventory,
-    pub collected_locations: LocationBitset,
-    spheres: Vec<Vec<(Location, Item)>>,
-    requirements: &'a Requirements<'a>,
-    placement: &'a Placement,
-    options: &'a Options,
+    worlds: Vec<(usize, WorldState, ExploredWorld<'a>)>,
 }
 
-pub fn collect_events(
-    requirements: &Requirements<'_>,
-    options: &Options,
-    inventory: &mut Inventory,
-) -> bool {
-    let mut did_change = false;
-    'outer: for event in Event::ALL.iter() {
-        if !inventory.has_event(*event) {
-            // TODO: previously events had multiple requirements, not actually needed?
-            if requirements.check(event.into(), inventory, options, TimeOfDay::all()) {
-                inventory.insert_event(*event);
-                did_change = true;
-                continue 'outer;
-            }
-        }
+impl<'a> Default for SphereExplorer<'a> {
+    fn default() -> Self {
+        Self::new()
     }
-    did_change
 }
 
-pub fn explore_areas(
-    requirements: &Requirements<'_>,
-    placement: &Placement,
-    options: &Options,
-    inventory: &mut Inventory,
-    banned_areas: Option<&HashSet<Area>>,
-    rev_connections: &HashMap<Entrance, Exit>,
-) -> bool {
-    let mut did_change = false;
-    for area in Area::ALL.iter() {
-        if let Some(banned) = banned_areas {
-            if banned.contains(area) {
-                continue;
+impl<'a> SphereExplorer<'a> {
+    pub fn new() -> Self {
+        SphereExplorer {
+            worlds: Default::default(),
+        }
+    }
+
+    pub fn add_world(
+        &mut self,
+        world: usize,
+        placement: &'a Placement,
+        requirements: &'a Requirements<'a>,
+        options: &'a Options,
+    ) {
+        if self.worlds.iter().any(|(id, _, _)| *id == world) {
+            panic!("duplicate world {world}");
+        }
+        self.worlds.push((
+            world,
+            WorldState::new(placement),
+            ExploredWorld {
+                banned_areas: None,
+                placement,
+                placed: None,
+                requirements,
+                options,
+            },
+        ));
+        self.worlds.sort_unstable_by_key(|(id, _, _)| *id);
+    }
+
+    /// the next sphere as (world of the location, location, item), empty if nothing new can be reached
+    /// the items are only collected after the whole sphere is found
+    pub fn next_sphere(&mut self) -> Vec<(usize, Location, Item)> {
+        let mut sphere = Vec::new();
+        let mut found_items = Vec::new();
+        let mut found = Vec::new();
+        for (world_id, state, world) in self.worlds.iter_mut() {
+            state.explore(world, &mut found);
+            // keep the order of the locations independent of the exploration
+            found.sort_unstable_by_key(|(location, _, _)| *location);
+            for (location, item_world, item) in found.drain(..) {
+                sphere.push((*world_id, location, item));
+                found_items.push((item_world, item));
             }
         }
-        // println!("checking {} - {}", area.stage.name(ctx), area.name);
-        let already_reached_tod = inventory.get_area_tod(*area);
-        let possible_tod = TimeOfDay::from_force_tod(area.get().time_of_day);
-        if already_reached_tod.contains(possible_tod) {
-            // this area is already fully accessible, no need to check it further
-            continue;
+        for (item_world, item) in found_items {
+            let (_, state, world) = self
+                .worlds
+                .iter_mut()
+                .find(|(id, _, _)| *id == item_world)
+                .unwrap();
+            state.insert_items(world.requirements, item, 1);
+        }
+        sphere
+    }
+
+    /// all spheres, until nothing new can be reached
+    pub fn collect_spheres(&mut self) -> Vec<Vec<(usize, Location, Item)>> {
+        let mut spheres = Vec::new();
+        loop {
+            let sphere = self.next_sphere();
+            if sphere.is_empty() {
+                return spheres;
+            }
+            spheres.push(sphere);
         }
+    }
+}
 
-        // try to sleep
-        if !already_reached_tod.is_empty() && area.get().can_sleep {
-            inventory.reachable_areas_day.insert(*area);
-            inventory.reachable_areas_night.insert(*area);
-            did_change = true;
-            // we now have reached everything possible, no need to check more
-            continue;
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_pcg::Pcg64;
+
+    use super::*;
+    use crate::{assumed_fill::run_with_seed, generated::get_logic, individual_world::SingleWorld};
+
+    /// checks every requirement again until nothing changes, so nothing depends on the
+    /// dependency index finding what has to be checked again
+    fn explore_full_rescan(state: &mut WorldState, world: &ExploredWorld<'_>) {
+        let mut found = Vec::new();
+        loop {
+            let before = state.clone();
+            for requirement in RequirementKey::ALL {
+                state.queue(*requirement);
+            }
+            state.explore(world, &mut found);
+            for (_, _, item) in found.drain(..) {
+                state.insert_items(world.requirements, item, 1);
+            }
+            if same_progress(&before, state) {
+                return;
+            }
         }
+    }
 
-        // check all entrances to this area
-        for entrance in area.get().map_entrances.iter() {
-            for exit in placement
-                .rev_connections
-                .get(entrance)
-                .map(|e| e.as_slice())
-                .unwrap_or_default()
-                .iter()
-                .chain(rev_connections.get(entrance))
-                .copied()
-            {
-                // we check this area for both ToD, to account for forced ToD changes
-                // if the area we're currently checking has a forced ToD, we don't care what ToD
-                // the exit is taken
-                if !possible_tod.is_all() {
-                    // ToD is forced
-                    // check if this area can be entered, ToD of the previous area doesn't matter
-                    if requirements.check(Into::into(exit), inventory, options, TimeOfDay::all()) {
-                        inventory.insert_area_tod(*area, possible_tod);
-                        did_change = true;
-                    }
-                } else {
-                    // ToD is not forced
-                    // check for both day and night if the exit can be taken
-                    for tod in [TimeOfDay::Day, TimeOfDay::Night] {
-                        // only check logic if the tod isn't already reached
-                        if !already_reached_tod.contains(tod)
-                            && requirements.check(exit.into(), inventory, options, tod)
-                        {
-                            // We might be able to save another area collection iteration by trying
-                            // to sleep immediately
-                            if area.get().can_sleep {
-                                inventory.insert_area_tod(*area, TimeOfDay::all());
-                            } else {
-                                inventory.insert_area_tod(*area, tod);
-                            }
-                            did_change = true;
-                        }
-                    }
-                }
+    fn same_progress(a: &WorldState, b: &WorldState) -> bool {
+        a.inventory.reachable_areas_day == b.inventory.reachable_areas_day
+            && a.inventory.reachable_areas_night == b.inventory.reachable_areas_night
+            && a.inventory.items == b.inventory.items
+            && a.inventory.events == b.inventory.events
+            && a.collected_locations == b.collected_locations
+    }
+
+    fn rescanned(world: &SingleWorld<'_>, placement: &Placement) -> WorldState {
+        let mut state = WorldState::new(placement);
+        let explored = ExploredWorld {
+            banned_areas: Some(&world.banned_areas),
+            placement,
+            placed: None,
+            requirements: &world.world_requirements,
+            options: &world.options,
+        };
+        explore_full_rescan(&mut state, &explored);
+        state
+    }
+
+    /// a finished placement with only some of the items
+    fn partial_placement(rng: &mut impl Rng, placement: &Placement, fraction: f64) -> Placement {
+        let mut partial = placement.clone();
+        let mut locations: Vec<_> = partial.locations.drain().collect();
+        locations.sort_unstable_by_key(|(location, _)| *location);
+        partial.locations = locations
+            .into_iter()
+            .filter(|_| rng.gen_bool(fraction))
+            .collect();
+        partial
+    }
+
+    fn finished_worlds<'a>(
+        options: &Options,
+        requirements: &'a Requirements<'static>,
+    ) -> Vec<SingleWorld<'a>> {
+        (0..)
+            .filter_map(|seed| run_with_seed(seed, options, requirements, 1).ok())
+            .take(3)
+            .flat_map(|filled| filled.worlds)
+            .collect()
+    }
+
+    #[test]
+    fn worklist_matches_full_rescan() {
+        let options = Options::default();
+        let requirements = get_logic(&options);
+        let mut rng = Pcg64::seed_from_u64(0);
+        for world in finished_worlds(&options, &requirements) {
+            for fraction in [0.0, 0.3, 0.7, 1.0] {
+                let placement = partial_placement(&mut rng, &world.placement, fraction);
+                let mut explorer = MultiworldExplorer::new();
+                explorer.add_world(
+                    0,
+                    Some(&world.banned_areas),
+                    &placement,
+                    &world.world_requirements,
+                    &world.options,
+                );
+                explorer.explore();
+                assert!(same_progress(
+                    &explorer.worlds[&0].state,
+                    &rescanned(&world, &placement)
+                ));
             }
         }
+    }
 
-        for exit_area in area.get().logic_entrances.iter() {
-            // ToD is not forced
-            // check for both day and night if the exit can be taken
-            for tod in [TimeOfDay::Day, TimeOfDay::Night] {
-                // only check logic if the tod isn't already reached
-                if !already_reached_tod.contains(tod)
-                    && requirements.check(
-                        RequirementKey::LogicExit {
-                            from: *exit_area,
-                            to: *area,
-                        },
-                        inventory,
-                        options,
-                        tod,
-                    )
-                {
-                    if area.get().can_sleep {
-                        inventory.insert_area_tod(*area, TimeOfDay::all());
-                    } else {
-                        inventory.insert_area_tod(*area, tod);
-                    }
-                    did_change = true;
+    #[test]
+    fn restored_snapshot_matches_full_rescan() {
+        let options = Options::default();
+        let requirements = get_logic(&options);
+        let mut rng = Pcg64::seed_from_u64(0);
+        for world in finished_worlds(&options, &requirements) {
+            let placement = partial_placement(&mut rng, &world.placement, 0.3);
+            let mut explorer = MultiworldExplorer::new();
+            explorer.add_world(
+                0,
+                Some(&world.banned_areas),
+                &placement,
+                &world.world_requirements,
+                &world.options,
+            );
+            explorer.explore();
+            let snapshot = explorer.snapshot();
+
+            // place some of the remaining items through the explorer, like the fill does
+            let mut combined = placement.clone();
+            let mut remaining: Vec<_> = world
+                .placement
+                .locations
+                .iter()
+                .filter(|(location, _)| !placement.locations.contains_key(location))
+                .map(|(location, placed)| (*location, *placed))
+                .collect();
+            remaining.sort_unstable_by_key(|(location, _)| *location);
+            for (location, (item_world, item)) in remaining {
+                if rng.gen_bool(0.5) {
+                    explorer.set_location(0, location, item_world, item);
+                    combined.set_location(location, item_world, item);
                 }
             }
+            let expected = rescanned(&world, &combined);
+
+            explorer.explore();
+            assert!(same_progress(&explorer.worlds[&0].state, &expected));
+
+            // the placed items are kept, so exploring again has to find the same
+            explorer.restore(&snapshot);
+            explorer.explore();
+            assert!(same_progress(&explorer.worlds[&0].state, &expected));
         }
     }
-    did_change
 }
-
-// pub fn collect_spheres(
-//     requirements: &Requirements<'_>,
-//     placement: &Placement,
-//     options: &Options,
-// ) -> Vec<Vec<(Location, Item)>> {
-//     let mut inventory = placement.get_initial_inventory();
-//     let mut collected_locations = LocationBitset::new();
-
-//     let mut spheres = Vec::new();
-//     let mut overall_did_change = true;
-//     while overall_did_change {
-//         overall_did_change = false;
-//         let mut cur_sphere = Vec::new();
-//         // fully explore areas
-//         while explore_areas(
-//             requirements,
-//             placement,
-//             options,
-//             &mut inventory,
-//             None,
-//             &HashMap::new(),
-//         ) {
-//             overall_did_change = true;
-//         }
-//         // fully collect events
-//         while collect_events(requirements, options, &mut inventory) {
-//             overall_did_change = true;
-//         }
-//         // all locations/item reachable now are the current sphere
-//         for (&location, &item) in &placement.locations {
-//             // only consider new, reachable locations
-//             if !collected_locations.has(location)
-//                 && requirements.check(location.into(), &inventory, options, TimeOfDay::all())
-//             {
-//                 collected_locations.insert(location);
-//                 cur_sphere.push((location, item));
-//             }
-//         }
-//         if !cur_sphere.is_empty() {
-//             overall_did_change = true;
-//             for (_, item) in &cur_sphere {
-//                 inventory.insert_item(*item);
-//             }
-//             spheres.push(cur_sphere);
-//         }
-//     }
-//     spheres
-// }
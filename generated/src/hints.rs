@@ -0,0 +1,322 @@
+use std::fmt::Display;
+
+use fxhash::FxHashSet as HashSet;
+use rand::prelude::*;
+
+use crate::{
+    assumed_fill::FilledWorlds,
+    explorer::MultiworldExplorer,
+    generated::{Item, Location, LocationKind, LogicMode, Options, Region},
+    individual_world::SingleWorld,
+    logic_static::BitSetCompatible,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    /// the region has an item that is required to beat the game
+    SpiritOfTheSword(Region),
+    /// the region has no item that is required to beat the game
+    Barren(Region),
+    /// the region has an item that may be required
+    Sometimes(Region),
+    /// the item at the location, and the world it's for if that isn't the world of the hint
+    Location(Location, Item, Option<usize>),
+    /// the region an item is in, and the world of the region if that isn't the world of the hint
+    Item(Item, Region, Option<usize>),
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hint::SpiritOfTheSword(region) => write!(
+                f,
+                "The Spirit of the Sword guides the goddess' chosen hero to {}",
+                region.name()
+            ),
+            Hint::Barren(region) => {
+                write!(
+                    f,
+                    "They say that those who travel to {} will find nothing of use",
+                    region.name()
+                )
+            }
+            Hint::Sometimes(region) => {
+                write!(
+                    f,
+                    "They say that {} may hold something useful",
+                    region.name()
+                )
+            }
+            Hint::Location(location, item, world) => {
+                write!(
+                    f,
+                    "They say that {} has {}",
+                    location.get().display_name,
+                    item.get().name
+                )?;
+                if let Some(world) = world {
+                    write!(f, " for World {world}")?;
+                }
+                Ok(())
+            }
+            Hint::Item(item, region, world) => {
+                write!(
+                    f,
+                    "They say that {} can be found in {}",
+                    item.get().name,
+                    region.name()
+                )?;
+                if let Some(world) = world {
+                    write!(f, " of World {world}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn is_gossip_stone(location: Location) -> bool {
+    matches!(location.get().kind, LocationKind::GossipStone { .. })
+}
+
+fn location_region(location: Location) -> Region {
+    location.get().area.get().region
+}
+
+/// picks up to `count` of the candidates at random, that aren't hinted yet
+fn pick<R: Rng, T: Copy + Eq + std::hash::Hash>(
+    rng: &mut R,
+    candidates: &[T],
+    count: usize,
+    hinted: &mut HashSet<T>,
+) -> Vec<T> {
+    let mut candidates: Vec<T> = candidates
+        .iter()
+        .filter(|candidate| !hinted.contains(candidate))
+        .copied()
+        .collect();
+    let mut seen = HashSet::default();
+    candidates.retain(|candidate| seen.insert(*candidate));
+    candidates.shuffle(rng);
+    candidates.truncate(count);
+    hinted.extend(candidates.iter().copied());
+    candidates
+}
+
+/// the hints of a world, according to the hint counts in its options
+fn world_hints<R: Rng>(
+    rng: &mut R,
+    world: &SingleWorld<'_>,
+    worlds: &[SingleWorld<'_>],
+    // `None` if it's unknown which items are required
+    required: Option<&HashSet<(usize, Location)>>,
+) -> Vec<Hint> {
+    let options: &Options = &world.options;
+    // (location, item world, item) for every item of this world, sorted so the hints are stable
+    let mut placed: Vec<(Location, usize, Item)> = world
+        .placement
+        .locations
+        .iter()
+        .filter(|(location, _)| !world.banned_areas.contains(&location.get().area))
+        .filter_map(|(location, (item_world, item))| {
+            item.as_item().map(|item| (*location, *item_world, item))
+        })
+        .collect();
+    placed.sort_unstable();
+    let is_hintable = |item_world: usize, item: Item| {
+        worlds[item_world]
+            .item_meta
+            .get(&item)
+            .is_some_and(|meta| meta.hintable)
+    };
+
+    // without knowing what is required, no region can be called required or barren
+    let mut required_regions: Vec<Region> = Vec::new();
+    let mut barren_regions: Vec<Region> = Vec::new();
+    let mut sometimes_regions: Vec<Region> = Vec::new();
+    if let Some(required) = required {
+        let is_required = |location: Location| required.contains(&(world.world_id, location));
+        required_regions = placed
+            .iter()
+            .filter(|(location, _, _)| is_required(*location))
+            .map(|(location, _, _)| location_region(*location))
+            .collect();
+        // an item off the minimal playthrough can still be needed, so a region with any
+        // hintable item isn't barren
+        let hintable_regions: Vec<Region> = placed
+            .iter()
+            .filter(|(_, item_world, item)| is_hintable(*item_world, *item))
+            .map(|(location, _, _)| location_region(*location))
+            .collect();
+        barren_regions = placed
+            .iter()
+            .map(|(location, _, _)| location_region(*location))
+            .filter(|region| {
+                !required_regions.contains(region) && !hintable_regions.contains(region)
+            })
+            .collect();
+        sometimes_regions = placed
+            .iter()
+            .filter(|(location, item_world, item)| {
+                is_hintable(*item_world, *item) && !is_required(*location)
+            })
+            .map(|(location, _, _)| location_region(*location))
+            .collect();
+    }
+    let hintable_locations: Vec<Location> = placed
+        .iter()
+        .filter(|(_, item_world, item)| is_hintable(*item_world, *item))
+        .map(|(location, _, _)| *location)
+        .collect();
+    // the items of this world, wherever they are
+    let mut own_items: Vec<(Item, usize, Location)> = worlds
+        .iter()
+        .flat_map(|other| {
+            other
+                .placement
+                .locations
+                .iter()
+                .filter(|(_, (item_world, _))| *item_world == world.world_id)
+                .filter_map(|(location, (_, item))| {
+                    item.as_item().map(|item| (item, other.world_id, *location))
+                })
+        })
+        .filter(|(item, _, _)| is_hintable(world.world_id, *item))
+        .collect();
+    own_items.sort_unstable();
+
+    // a region is only hinted once, whatever kind of hint it gets
+    let mut hinted_regions = HashSet::default();
+    let mut hinted_locations = HashSet::default();
+    let mut hints = Vec::new();
+    for region in pick(
+        rng,
+        &required_regions,
+        options.sots_hints,
+        &mut hinted_regions,
+    ) {
+        hints.push(Hint::SpiritOfTheSword(region));
+    }
+    for region in pick(
+        rng,
+        &barren_regions,
+        options.barren_hints,
+        &mut hinted_regions,
+    ) {
+        hints.push(Hint::Barren(region));
+    }
+    for region in pick(
+        rng,
+        &sometimes_regions,
+        options.sometimes_hints,
+        &mut hinted_regions,
+    ) {
+        hints.push(Hint::Sometimes(region));
+    }
+    for location in pick(
+        rng,
+        &hintable_locations,
+        options.location_hints,
+        &mut hinted_locations,
+    ) {
+        let (_, item_world, item) = placed
+            .iter()
+            .find(|(placed_location, _, _)| *placed_location == location)
+            .unwrap();
+        let item_world = (*item_world != world.world_id).then_some(*item_world);
+        hints.push(Hint::Location(location, *item, item_world));
+    }
+    let item_locations: Vec<(usize, Location)> = own_items
+        .iter()
+        .filter(|(_, location_world, location)| {
+            // the location hints already tell these
+            *location_world != world.world_id || !hinted_locations.contains(location)
+        })
+        .map(|(_, location_world, location)| (*location_world, *location))
+        .collect();
+    let mut hinted_items = HashSet::default();
+    for (location_world, location) in
+        pick(rng, &item_locations, options.item_hints, &mut hinted_items)
+    {
+        let (item, _, _) = own_items
+            .iter()
+            .find(|(_, other_world, other)| *other_world == location_world && *other == location)
+            .unwrap();
+        let location_world = (location_world != world.world_id).then_some(location_world);
+        hints.push(Hint::Item(*item, location_region(location), location_world));
+    }
+    hints
+}
+
+/// computes the hints of every world from the finished placements and spreads them over the
+/// gossip stones that can be reached, returns (stone, hints) for every world
+pub fn generate_hints<R: Rng>(
+    rng: &mut R,
+    filled: &FilledWorlds<'_>,
+) -> Vec<Vec<(Location, Vec<Hint>)>> {
+    let worlds = &filled.worlds;
+    let wants_hints = worlds.iter().any(|world| {
+        let options = &world.options;
+        options.sots_hints
+            + options.barren_hints
+            + options.sometimes_hints
+            + options.location_hints
+            + options.item_hints
+            != 0
+    });
+    if !wants_hints {
+        return worlds.iter().map(|_| Vec::new()).collect();
+    }
+    // without logic, the playthrough doesn't tell what is actually needed
+    let no_logic = worlds
+        .iter()
+        .any(|world| world.options.logic_mode == LogicMode::NoLogic);
+    let required: Option<HashSet<(usize, Location)>> = if no_logic {
+        None
+    } else {
+        filled.playthrough().map(|playthrough| {
+            playthrough
+                .iter()
+                .flatten()
+                .map(|(world, location, _)| (*world, *location))
+                .collect()
+        })
+    };
+    let mut explorer = MultiworldExplorer::new();
+    for world in worlds.iter() {
+        explorer.add_world(
+            world.world_id,
+            Some(&world.banned_areas),
+            &world.placement,
+            &world.world_requirements,
+            &world.options,
+        );
+    }
+    worlds
+        .iter()
+        .map(|world| {
+            let hints = world_hints(rng, world, worlds, required.as_ref());
+            let mut stones: Vec<Location> = Location::ALL
+                .iter()
+                .filter(|location| {
+                    is_gossip_stone(**location) && explorer.can_reach(world.world_id, **location)
+                })
+                .copied()
+                .collect();
+            stones.shuffle(rng);
+            let mut stone_hints: Vec<(Location, Vec<Hint>)> = stones
+                .into_iter()
+                .map(|stone| (stone, Vec::new()))
+                .collect();
+            if !stone_hints.is_empty() {
+                let stone_count = stone_hints.len();
+                for (index, hint) in hints.into_iter().enumerate() {
+                    stone_hints[index % stone_count].1.push(hint);
+                }
+            }
+            stone_hints.retain(|(_, hints)| !hints.is_empty());
+            stone_hints.sort_unstable_by_key(|(stone, _)| *stone);
+            stone_hints
+        })
+        .collect()
+}
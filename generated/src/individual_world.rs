@@ -1,31 +1,78 @@
-use std::{
-    iter::repeat,
-};
 use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::iter::{repeat, repeat_n};
 
 use rand::prelude::*;
+use snafu::{ResultExt, Snafu};
 
 use crate::{
     constants::{
-        Dungeon, CONSUMABLE_ITEMS, DUNGEON_RESTRICTION_INFO, NONPROGRESS_ITEMS, PROGRESS_ITEMS,
+        junk_distribution, Dungeon, CONSUMABLE_ITEMS, DUNGEON_RESTRICTION_INFO, NONPROGRESS_ITEMS,
+        PROGRESS_ITEMS, REGION_TYPES,
+    },
+    entrance_rando::{
+        randomize_all_entrances, randomize_dungeon_entrances, randomize_start,
+        randomize_trial_gates, shuffled_connections,
     },
-    explorer::Placement,
+    explorer::{MultiworldExplorer, Placement, SphereExplorer},
     generated::{
-        Area, BossKeyMode, Entrance, Event, Exit, GotDungeonRequirement, GotStart, Item, Location,
-        MapMode, Options, Region, ShopMode, SmallKeyMode, Stage, StartingSword,
+        Area, BannedTypes, BossKeyMode, Entrance, Event, Exit, GotDungeonRequirement, GotStart,
+        Item, Location, LogicMode, MapMode, Options, RandomStartingEntrance, RandomizeEntrances,
+        Region, ShopMode, ShuffleAllEntrances, SmallKeyMode, Stage, StartingSword,
     },
+    hints::{is_gossip_stone, Hint},
     logic_static::{
         BitSetCompatible, Inventory, RequirementExpression, RequirementKey, Requirements, TimeOfDay,
     },
     plando::{
         self, place_items_plando, LocationOrStart, PlandoEntry, WeightedItem, WeightedLocation,
     },
+    playthrough::GOAL,
     util::sample_stable,
 };
 
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("{source}"))]
+    Plando { source: plando::Error },
+    #[snafu(display(
+        "{progress_items} progress items, but only {progress_locations} progress locations are left"
+    ))]
+    TooManyProgressItems {
+        progress_items: usize,
+        progress_locations: usize,
+    },
+    #[snafu(display(
+        "{items} items that aren't consumables, but only {locations} locations are left"
+    ))]
+    TooManyItems { items: usize, locations: usize },
+    #[snafu(display("Cannot beat the game with all items, because of the entrances"))]
+    Unbeatable,
+    #[snafu(display("Only {reachable} locations can be reached from the start"))]
+    DeadStart { reachable: usize },
+    #[snafu(display(
+        "Randomize Silent Realms isn't supported yet, the trial entrances still have to be checked in game"
+    ))]
+    UncheckedTrialEntrances,
+}
+
+impl Error {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Plando { source } => source.is_retryable(),
+            Self::Unbeatable | Self::DeadStart { .. } => true,
+            // the pool only depends on settings and plando
+            Self::TooManyProgressItems { .. }
+            | Self::TooManyItems { .. }
+            | Self::UncheckedTrialEntrances => false,
+        }
+    }
+}
+
 pub struct ItemMeta {
     pub hintable: bool,
     pub progress_loc: bool,
+    /// placed randomly after all other items, without logic
+    pub consumable: bool,
 }
 
 pub struct SingleWorld<'a> {
@@ -38,6 +85,8 @@ pub struct SingleWorld<'a> {
     pub item_meta: HashMap<Item, ItemMeta>,
     pub options: Options,
     pub locations: Vec<Location>,
+    /// the hints on every gossip stone that has any
+    pub hints: Vec<(Location, Vec<Hint>)>,
 }
 
 impl<'a> SingleWorld<'a> {
@@ -54,6 +103,40 @@ impl<'a> SingleWorld<'a> {
         for start_item in &start_items {
             writeln!(out, "{}", start_item.get().name)?;
         }
+        if let Some((entrance, tod)) = self.placement.initial_entrance {
+            writeln!(out)?;
+            let tod = if tod == TimeOfDay::Night {
+                "Night"
+            } else {
+                "Day"
+            };
+            writeln!(out, "Starting at {} ({tod})", entrance.get().display_name)?;
+        }
+        let connections = shuffled_connections(&self.placement);
+        if !connections.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Entrances")?;
+            writeln!(out, "=========")?;
+            for (exit, entrance) in connections {
+                writeln!(
+                    out,
+                    "{} -> {}",
+                    exit.get().display_name,
+                    entrance.get().display_name
+                )?;
+            }
+        }
+        if !self.hints.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Hints")?;
+            writeln!(out, "=====")?;
+            for (stone, hints) in self.hints.iter() {
+                writeln!(out, "{}:", stone.get().display_name)?;
+                for hint in hints {
+                    writeln!(out, "    {hint}")?;
+                }
+            }
+        }
         let max_loc_len = Region::ALL
             .iter()
             .flat_map(|r| r.areas())
@@ -94,6 +177,50 @@ impl<'a> SingleWorld<'a> {
     }
 }
 
+/// the spheres of all worlds together, see `SphereExplorer`
+pub fn collect_spheres(worlds: &[SingleWorld<'_>]) -> Vec<Vec<(usize, Location, Item)>> {
+    let mut explorer = SphereExplorer::new();
+    for world in worlds {
+        explorer.add_world(
+            world.world_id,
+            &world.placement,
+            &world.world_requirements,
+            &world.options,
+        );
+    }
+    explorer.collect_spheres()
+}
+
+pub fn write_spheres(
+    out: &mut impl std::fmt::Write,
+    title: &str,
+    spheres: &[Vec<(usize, Location, Item)>],
+    multiworld: bool,
+) -> std::fmt::Result {
+    writeln!(out, "{title}")?;
+    writeln!(out, "{:=<1$}", "", title.len())?;
+    for (sphere_idx, sphere) in spheres.iter().enumerate() {
+        writeln!(out)?;
+        writeln!(out, "Sphere {}", sphere_idx + 1)?;
+        for (world, loc, item) in sphere {
+            if multiworld {
+                write!(out, "World {world}: ")?;
+            }
+            writeln!(out, "{}: {}", loc.get().display_name, item.get().name)?;
+        }
+    }
+    Ok(())
+}
+
+/// if the location has a banned type, region types don't count for dungeon checks
+fn is_banned_type(loc: Location, options: &Options) -> bool {
+    let types = loc.get().types;
+    let is_dungeon = types.contains(&BannedTypes::Dungeon);
+    types.iter().any(|check_type| {
+        options.banned_types.has(*check_type) && !(is_dungeon && REGION_TYPES.contains(check_type))
+    })
+}
+
 // randomizes a single world, as long as it doesn't need to interact with other worlds
 
 pub fn generate_single_world<'a, R: Rng>(
@@ -101,7 +228,11 @@ pub fn generate_single_world<'a, R: Rng>(
     options: Options,
     base_requirements: &'a Requirements<'static>,
     world_index: usize,
-) -> Result<SingleWorld<'a>, plando::Error> {
+) -> Result<SingleWorld<'a>, Error> {
+    // the shuffled gates couldn't be patched without the entrances
+    if options.randomize_trials {
+        return Err(Error::UncheckedTrialEntrances);
+    }
     let required_dungeons: Vec<_> = sample_stable(
         rng,
         Dungeon::POTENTIALLY_REQUIRED,
@@ -112,11 +243,14 @@ pub fn generate_single_world<'a, R: Rng>(
     // TODO: only if option requires it
     world_requirements.set_requirement(
         Event::TriforcesCollected.into(),
-        RequirementExpression::And(vec![
-            RequirementExpression::Item(Item::TriforceOfCourage, 1),
-            RequirementExpression::Item(Item::TriforceOfPower, 1),
-            RequirementExpression::Item(Item::TriforceOfWisdom, 1),
-        ]),
+        RequirementExpression::And(
+            vec![
+                RequirementExpression::Item(Item::TriforceOfCourage, 1),
+                RequirementExpression::Item(Item::TriforceOfPower, 1),
+                RequirementExpression::Item(Item::TriforceOfWisdom, 1),
+            ]
+            .into(),
+        ),
     );
     let req_dungeons_reqs = required_dungeons
         .iter()
@@ -140,17 +274,30 @@ pub fn generate_single_world<'a, R: Rng>(
             ItemMeta {
                 hintable: true,
                 progress_loc: true,
+                consumable: false,
             },
         );
         useless_items.insert(*item);
     }
-    for (item, count) in NONPROGRESS_ITEMS.iter().chain(CONSUMABLE_ITEMS.iter()) {
+    for (item, count) in NONPROGRESS_ITEMS {
         item_pool.insert(*item, *count);
         item_meta.insert(
             *item,
             ItemMeta {
                 hintable: false,
                 progress_loc: false,
+                consumable: false,
+            },
+        );
+    }
+    for (item, count) in CONSUMABLE_ITEMS {
+        item_pool.insert(*item, *count);
+        item_meta.insert(
+            *item,
+            ItemMeta {
+                hintable: false,
+                progress_loc: false,
+                consumable: true,
             },
         );
     }
@@ -164,7 +311,6 @@ pub fn generate_single_world<'a, R: Rng>(
         .initial_events
         .insert(Event::LanayruMineEntryStatue);
 
-    // TODO: entrance randomizer
     placement.initial_entrance = Some((
         Entrance::KnightAcademy_From_Skyloft_Lower_Left,
         TimeOfDay::Day,
@@ -174,6 +320,33 @@ pub fn generate_single_world<'a, R: Rng>(
             placement.connect(entrance, *exit);
         }
     }
+    let shuffled_exits: HashSet<Exit> =
+        randomize_dungeon_entrances(rng, &mut placement, &options, &required_dungeons)
+            .into_iter()
+            .collect();
+    randomize_start(rng, &mut placement, &options);
+    // keys have to be reachable without themselves, so the shuffle connects everything without keys
+    let is_key = |item: Item| {
+        item == Item::LanayruCavesSmallKey
+            || DUNGEON_RESTRICTION_INFO.iter().any(|info| {
+                info.small_key.is_some_and(|(key, _)| key == item)
+                    || info.boss_key.is_some_and(|(key, _)| key == item)
+            })
+    };
+    let keyless_pool: HashMap<Item, u8> = item_pool
+        .iter()
+        .filter(|(item, _)| !is_key(**item))
+        .map(|(item, count)| (*item, *count))
+        .collect();
+    randomize_all_entrances(
+        rng,
+        &mut placement,
+        &options,
+        &shuffled_exits,
+        &world_requirements,
+        &keyless_pool,
+    );
+    randomize_trial_gates(rng, &mut placement, &options);
 
     let banned_areas: HashSet<Area> = Dungeon::POTENTIALLY_REQUIRED
         .iter()
@@ -182,6 +355,28 @@ pub fn generate_single_world<'a, R: Rng>(
         .copied()
         .collect();
 
+    // shuffled entrances can make the game unbeatable even with all items,
+    // without logic the requirements don't tell if it's beatable
+    if options.logic_mode != LogicMode::NoLogic
+        && (options.randomize_entrances != RandomizeEntrances::None
+            || options.shuffle_all_entrances != ShuffleAllEntrances::Off)
+    {
+        let mut explorer = MultiworldExplorer::new();
+        explorer.add_world(
+            world_index,
+            Some(&banned_areas),
+            &placement,
+            &world_requirements,
+            &options,
+        );
+        for (item, count) in item_pool.iter() {
+            explorer.insert_items(world_index, *item, *count);
+        }
+        if !explorer.can_reach(world_index, GOAL) {
+            return Err(Error::Unbeatable);
+        }
+    }
+
     let empty_inventory = Inventory::default();
     let mut progress_locations = HashSet::default();
 
@@ -192,11 +387,19 @@ pub fn generate_single_world<'a, R: Rng>(
         let area_def = area.get();
         // check locations
         for loc in area_def.locations {
+            // locations of a banned type never get progress items, so the items only
+            // needed for them aren't progress either
+            if is_banned_type(*loc, &options) {
+                continue;
+            }
             world_requirements
                 .get_requirement(loc.into())
                 .unwrap()
                 .remove_used_items(&empty_inventory, &options, &mut useless_items);
-            progress_locations.insert(*loc);
+            // gossip stones hold hints, not items
+            if !is_gossip_stone(*loc) {
+                progress_locations.insert(*loc);
+            }
         }
         // TODO: events could be bound to areas as well
         for exit_area in area_def.logic_exits {
@@ -226,13 +429,18 @@ pub fn generate_single_world<'a, R: Rng>(
             ItemMeta {
                 hintable: false,
                 progress_loc: false,
+                consumable: false,
             },
         );
     }
 
     // generate plando entries
     let entries = plando_entries_for_options(&options);
-    let mut locations = Location::ALL.iter().cloned().collect();
+    let mut locations = Location::ALL
+        .iter()
+        .filter(|loc| !is_gossip_stone(**loc))
+        .cloned()
+        .collect();
     let progress_items = item_meta
         .iter()
         .filter_map(|(item, meta)| (meta.progress_loc).then_some(*item))
@@ -248,17 +456,49 @@ pub fn generate_single_world<'a, R: Rng>(
         &progress_locations,
         &progress_items,
         world_index,
+    )
+    .context(PlandoSnafu)?;
+
+    let items = build_item_pool(
+        rng,
+        &item_pool,
+        &mut item_meta,
+        &locations,
+        &progress_locations,
+        junk_distribution(options.junk_distribution),
     )?;
 
+    // a random start or shuffled entrances can leave almost nothing to do at the start,
+    // without logic everything counts as reachable anyway
+    if options.logic_mode != LogicMode::NoLogic
+        && (options.random_starting_entrance != RandomStartingEntrance::Vanilla
+            || options.random_starting_tod
+            || options.randomize_entrances != RandomizeEntrances::None
+            || options.shuffle_all_entrances != ShuffleAllEntrances::Off)
+    {
+        // only the starting items, not the items placed by plando
+        let mut start_placement = placement.clone();
+        start_placement.locations.clear();
+        let mut explorer = MultiworldExplorer::new();
+        explorer.add_world(
+            world_index,
+            Some(&banned_areas),
+            &start_placement,
+            &world_requirements,
+            &options,
+        );
+        let reachable = locations
+            .iter()
+            .filter(|loc| explorer.can_reach(world_index, **loc))
+            .count();
+        if reachable < options.min_starting_locations {
+            return Err(Error::DeadStart { reachable });
+        }
+    }
+
     let mut locations: Vec<Location> = locations.into_iter().collect();
     locations.sort_unstable();
 
-    let mut items: Vec<Item> = item_pool
-        .into_iter()
-        .flat_map(|(item, count)| repeat(item).take(count as usize))
-        .collect();
-    items.sort_unstable();
-
     Ok(SingleWorld {
         world_id: world_index,
         banned_areas,
@@ -269,9 +509,74 @@ pub fn generate_single_world<'a, R: Rng>(
         world_requirements,
         options,
         locations,
+        hints: Vec::new(),
     })
 }
 
+/// the items left after plando, balanced so that there is exactly one item for every free location
+/// consumables are removed at random when there are too many items, junk is added when there are too few
+pub fn build_item_pool<R: Rng>(
+    rng: &mut R,
+    item_pool: &HashMap<Item, u8>,
+    item_meta: &mut HashMap<Item, ItemMeta>,
+    locations: &HashSet<Location>,
+    progress_locations: &HashSet<Location>,
+    // (item, weight)
+    junk_distribution: &[(Item, u32)],
+) -> Result<Vec<Item>, Error> {
+    let mut items: Vec<Item> = item_pool
+        .iter()
+        .flat_map(|(item, count)| repeat_n(*item, *count as usize))
+        .collect();
+    // the iteration order of the map isn't stable
+    items.sort_unstable();
+    let (mut consumables, items): (Vec<Item>, Vec<Item>) = items
+        .into_iter()
+        .partition(|item| item_meta.get(item).is_some_and(|meta| meta.consumable));
+
+    let progress_items = items
+        .iter()
+        .filter(|item| item_meta.get(item).is_some_and(|meta| meta.progress_loc))
+        .count();
+    let free_progress_locations = locations
+        .iter()
+        .filter(|loc| progress_locations.contains(loc))
+        .count();
+    if progress_items > free_progress_locations {
+        return Err(Error::TooManyProgressItems {
+            progress_items,
+            progress_locations: free_progress_locations,
+        });
+    }
+    if items.len() > locations.len() {
+        return Err(Error::TooManyItems {
+            items: items.len(),
+            locations: locations.len(),
+        });
+    }
+
+    let consumable_count = locations.len() - items.len();
+    if consumables.len() > consumable_count {
+        consumables.shuffle(rng);
+        consumables.truncate(consumable_count);
+    }
+    while consumables.len() < consumable_count {
+        let (junk, _) = junk_distribution
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .unwrap();
+        item_meta.entry(*junk).or_insert(ItemMeta {
+            hintable: false,
+            progress_loc: false,
+            consumable: true,
+        });
+        consumables.push(*junk);
+    }
+
+    let mut items: Vec<Item> = items.into_iter().chain(consumables).collect();
+    items.sort_unstable();
+    Ok(items)
+}
+
 pub fn plando_entries_for_options(options: &Options) -> Vec<PlandoEntry> {
     let mut entries = Vec::new();
     fn nonforced_vanilla(entries: &mut Vec<PlandoEntry>, item: Item, loc: Location) {
@@ -510,3 +815,40 @@ pub fn plando_entries_for_options(options: &Options) -> Vec<PlandoEntry> {
     });
     entries
 }
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Pcg64;
+
+    use super::*;
+    use crate::generated::get_logic;
+
+    #[test]
+    fn dead_starts_are_rejected() {
+        let options = Options {
+            random_starting_entrance: RandomStartingEntrance::Any,
+            ..Options::default()
+        };
+        let requirements = get_logic(&options);
+        let generate = |seed, options: &Options| {
+            let mut rng = Pcg64::seed_from_u64(seed);
+            generate_single_world(&mut rng, options.clone(), &requirements, 0)
+        };
+        let (seed, reachable) = (0..1000)
+            .find_map(|seed| match generate(seed, &options) {
+                Err(Error::DeadStart { reachable }) => Some((seed, reachable)),
+                _ => None,
+            })
+            .expect("no start was rejected");
+        assert!(reachable < options.min_starting_locations);
+        // the same start is fine if it doesn't have to reach anything
+        let allow_all = Options {
+            min_starting_locations: 0,
+            ..options
+        };
+        assert!(!matches!(
+            generate(seed, &allow_all),
+            Err(Error::DeadStart { .. })
+        ));
+    }
+}
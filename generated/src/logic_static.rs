@@ -1,10 +1,12 @@
-use std::{
-    marker::PhantomData,
-};
+use std::{marker::PhantomData, ops::Deref};
 
 use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use snafu::Snafu;
 
-use crate::generated::{Area, Event, Exit, Item, Location, Options, Stage};
+use crate::generated::{
+    Area, Event, Exit, Item, Location, LogicMode, Options, Stage, Trick, LOGIC_EXIT_COUNT,
+    REQUIREMENT_KEYS, REQUIREMENT_KEY_COUNT,
+};
 
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -25,6 +27,22 @@ impl TimeOfDay {
     }
 }
 
+#[derive(Debug, Snafu)]
+pub enum SetOptionError {
+    #[snafu(display("unknown option {command}"))]
+    UnknownOption { command: String },
+    #[snafu(display("{value} is not a valid value for {command}"))]
+    InvalidValue { command: String, value: String },
+}
+
+/// the choices of a multichoice option, separated by commas
+pub fn split_choices(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|choice| !choice.is_empty())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ForceToD {
     Day,
@@ -69,6 +87,13 @@ impl<T: BitSetCompatible, const N: usize> BitSet<T, N> {
         self.slots[slot] |= 1 << shift;
     }
 
+    pub fn remove(&mut self, t: T) {
+        let num: usize = t.into();
+        let slot = num / usize::BITS as usize;
+        let shift = num % usize::BITS as usize;
+        self.slots[slot] &= !(1 << shift);
+    }
+
     pub fn has(&self, t: T) -> bool {
         let num: usize = t.into();
         let slot = num / usize::BITS as usize;
@@ -85,10 +110,23 @@ impl<T: BitSetCompatible, const N: usize> BitSet<T, N> {
     }
 }
 
+impl<T: BitSetCompatible, const N: usize> FromIterator<T> for BitSet<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for t in iter {
+            set.insert(t);
+        }
+        set
+    }
+}
+
 pub type AreaBitset = BitSet<Area, { Area::ALL.len() / usize::BITS as usize + 1 }>;
 pub type EventBitset = BitSet<Event, { Event::ALL.len() / usize::BITS as usize + 1 }>;
 pub type StageBitset = BitSet<Stage, { Stage::ALL.len() / usize::BITS as usize + 1 }>;
 pub type LocationBitset = BitSet<Location, { Location::ALL.len() / usize::BITS as usize + 1 }>;
+pub type TrickBitset = BitSet<Trick, { Trick::ALL.len() / usize::BITS as usize + 1 }>;
+pub type RequirementKeyBitset =
+    BitSet<RequirementKey, { REQUIREMENT_KEY_COUNT / usize::BITS as usize + 1 }>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ItemCollection {
     count: [u8; Item::ALL.len()],
@@ -174,11 +212,41 @@ impl Inventory {
     }
 }
 
+/// the requirements generated by the dumper, for every `RequirementKey`, by its index
+#[derive(Clone, Copy)]
+pub struct RequirementTables {
+    pub requirements: &'static [&'static RequirementExpression<'static>],
+    // compiled versions of the requirements, used instead for checking
+    pub bytecode: &'static [&'static [Instruction]],
+    // the indices of the requirements that mention an item, event or area
+    pub item_dependents: &'static [&'static [u16]],
+    pub event_dependents: &'static [&'static [u16]],
+    pub area_dependents: &'static [&'static [u16]],
+}
+
+/// something requirements depend on, that can be gained while exploring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dependency {
+    Item(Item),
+    Event(Event),
+    Area(Area),
+}
+
 pub struct Requirements<'a> {
     parent: Option<&'a Requirements<'a>>,
     pub requirements: HashMap<RequirementKey, RequirementExpression<'a>>,
+    // what the requirements of this layer depend on, set_requirement keeps this up to date
+    dependents: HashMap<Dependency, Vec<RequirementKey>>,
+    // only the base layer has them, requirements of this layer take precedence
+    tables: Option<RequirementTables>,
 }
 
+// the requirements are shared by all threads generating seeds
+const _: () = {
+    const fn assert_sync<T: Sync>() {}
+    assert_sync::<Requirements<'static>>();
+};
+
 impl Default for Requirements<'static> {
     fn default() -> Self {
         Self::new()
@@ -187,15 +255,20 @@ impl Default for Requirements<'static> {
 
 impl Requirements<'static> {
     pub fn new() -> Self {
-        Self::new_from_map(Default::default())
+        Self {
+            parent: None,
+            requirements: Default::default(),
+            dependents: Default::default(),
+            tables: None,
+        }
     }
 
-    pub fn new_from_map(
-        requirements: HashMap<RequirementKey, RequirementExpression<'static>>,
-    ) -> Self {
+    pub fn new_from_tables(tables: RequirementTables) -> Self {
         Self {
             parent: None,
-            requirements,
+            requirements: Default::default(),
+            dependents: Default::default(),
+            tables: Some(tables),
         }
     }
 }
@@ -210,6 +283,13 @@ impl<'a> Requirements<'a> {
     ) -> bool {
         if let Some(req) = self.requirements.get(&requirement) {
             req.check(inventory, options, allowed_tod)
+        } else if let Some(tables) = &self.tables {
+            check_bytecode(
+                tables.bytecode[requirement.index()],
+                inventory,
+                options,
+                allowed_tod,
+            )
         } else if let Some(parent) = self.parent {
             parent.check(requirement, inventory, options, allowed_tod)
         } else {
@@ -221,6 +301,8 @@ impl<'a> Requirements<'a> {
         Requirements {
             parent: Some(self),
             requirements: Default::default(),
+            dependents: Default::default(),
+            tables: None,
         }
     }
 
@@ -230,11 +312,16 @@ impl<'a> Requirements<'a> {
     ) -> Option<RequirementExpression<'a>> {
         match self.requirements.get(&requirement) {
             Some(req) => Some(req.clone()),
-            None => self.parent.and_then(|parent| {
-                parent
-                    .get_requirement(requirement)
-                    .map(RequirementExpression::Ref)
-            }),
+            None => self
+                .tables
+                .map(|tables| RequirementExpression::Ref(tables.requirements[requirement.index()]))
+                .or_else(|| {
+                    self.parent.and_then(|parent| {
+                        parent
+                            .get_requirement(requirement)
+                            .map(RequirementExpression::Ref)
+                    })
+                }),
         }
     }
 
@@ -244,14 +331,45 @@ impl<'a> Requirements<'a> {
     ) -> Option<&'a RequirementExpression<'a>> {
         self.requirements
             .get(&requirement)
+            .or_else(|| {
+                self.tables
+                    .map(|tables| tables.requirements[requirement.index()])
+            })
             .or_else(|| self.parent.and_then(|p| p.get_requirement(requirement)))
     }
 
+    /// calls `f` with every requirement that mentions the dependency in any layer,
+    /// it can also be called with requirements that were replaced since
+    pub fn for_each_dependent(&self, dependency: Dependency, f: &mut impl FnMut(RequirementKey)) {
+        if let Some(dependents) = self.dependents.get(&dependency) {
+            dependents.iter().copied().for_each(&mut *f);
+        }
+        if let Some(tables) = &self.tables {
+            let indices = match dependency {
+                Dependency::Item(item) => tables.item_dependents[item as usize],
+                Dependency::Event(event) => tables.event_dependents[event as usize],
+                Dependency::Area(area) => tables.area_dependents[area as usize],
+            };
+            for index in indices {
+                f(REQUIREMENT_KEYS[*index as usize]);
+            }
+        }
+        if let Some(parent) = self.parent {
+            parent.for_each_dependent(dependency, f);
+        }
+    }
+
     pub fn set_requirement(
         &mut self,
         requirement_key: RequirementKey,
         requirement_expression: RequirementExpression<'a>,
     ) {
+        requirement_expression.for_each_dependency(&mut |dependency| {
+            let dependents = self.dependents.entry(dependency).or_default();
+            if !dependents.contains(&requirement_key) {
+                dependents.push(requirement_key);
+            }
+        });
         self.requirements
             .insert(requirement_key, requirement_expression);
     }
@@ -266,22 +384,60 @@ impl<'a> Requirements<'a> {
     ) {
         // TODO: the requirements should really exist in all cases, but should there be
         // a default just in case? But this should be fine since it signals a bug
-        let combined = RequirementExpression::And(vec![
-            self.get_owned_requirement(requirement).unwrap(),
-            self.get_owned_requirement(combined_requirement).unwrap(),
-        ]);
-        self.requirements.insert(combined_requirement, combined);
+        let combined = RequirementExpression::And(
+            vec![
+                self.get_owned_requirement(requirement).unwrap(),
+                self.get_owned_requirement(combined_requirement).unwrap(),
+            ]
+            .into(),
+        );
+        self.set_requirement(combined_requirement, combined);
+    }
+}
+
+/// the parts of an `And` or `Or`, either in the generated tables or built at runtime
+/// like a `Cow`, which would make `RequirementExpression` invariant over its lifetime
+#[derive(Clone)]
+pub enum ExpressionList<'a> {
+    Borrowed(&'a [RequirementExpression<'a>]),
+    Owned(Vec<RequirementExpression<'a>>),
+}
+
+impl<'a> Deref for ExpressionList<'a> {
+    type Target = [RequirementExpression<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ExpressionList::Borrowed(exprs) => exprs,
+            ExpressionList::Owned(exprs) => exprs,
+        }
+    }
+}
+
+impl<'a> From<Vec<RequirementExpression<'a>>> for ExpressionList<'a> {
+    fn from(value: Vec<RequirementExpression<'a>>) -> Self {
+        ExpressionList::Owned(value)
+    }
+}
+
+impl<'a> FromIterator<RequirementExpression<'a>> for ExpressionList<'a> {
+    fn from_iter<I: IntoIterator<Item = RequirementExpression<'a>>>(iter: I) -> Self {
+        ExpressionList::Owned(iter.into_iter().collect())
     }
 }
 
 #[derive(Clone)]
 pub enum RequirementExpression<'a> {
-    And(Vec<RequirementExpression<'a>>),
-    Or(Vec<RequirementExpression<'a>>),
+    And(ExpressionList<'a>),
+    Or(ExpressionList<'a>),
     Item(Item, u8),
     Event(Event),
     Area(Area, TimeOfDay),
     Fixed(bool),
+    Trick(Trick),
+    // the explorers and the fill assume that collecting more never makes anything
+    // unreachable again, so the codegen only allows this on options and tricks
+    Not(&'a RequirementExpression<'a>),
     Option(fn(&Options) -> bool),
     Ref(&'a RequirementExpression<'a>),
 }
@@ -303,11 +459,32 @@ impl<'a> RequirementExpression<'a> {
                 .intersection(*tod)
                 .is_empty(),
             RequirementExpression::Fixed(val) => *val,
+            RequirementExpression::Trick(trick) => trick_enabled(options, *trick),
+            RequirementExpression::Not(expr) => !expr.check(inventory, options, allowed_tod),
             RequirementExpression::Option(f) => f(options),
             RequirementExpression::Ref(req) => req.check(inventory, options, allowed_tod),
         }
     }
 
+    /// calls `f` with every item, event and area this mentions
+    pub fn for_each_dependency(&self, f: &mut impl FnMut(Dependency)) {
+        match self {
+            RequirementExpression::And(exprs) | RequirementExpression::Or(exprs) => {
+                for expr in exprs.iter() {
+                    expr.for_each_dependency(f);
+                }
+            }
+            RequirementExpression::Item(item, _) => f(Dependency::Item(*item)),
+            RequirementExpression::Event(event) => f(Dependency::Event(*event)),
+            RequirementExpression::Area(area, _) => f(Dependency::Area(*area)),
+            RequirementExpression::Not(expr) => expr.for_each_dependency(f),
+            RequirementExpression::Ref(expr) => expr.for_each_dependency(f),
+            RequirementExpression::Fixed(_)
+            | RequirementExpression::Trick(_)
+            | RequirementExpression::Option(_) => (),
+        }
+    }
+
     pub fn remove_used_items(
         &self,
         inventory: &Inventory,
@@ -328,33 +505,90 @@ impl<'a> RequirementExpression<'a> {
                 RequirementExpression::Event(..) => (),
                 RequirementExpression::Area(..) => (),
                 RequirementExpression::Fixed(..) => (),
+                RequirementExpression::Trick(..) => (),
+                // having the items of a negation never helps, so they aren't used
+                RequirementExpression::Not(..) => (),
                 RequirementExpression::Option(..) => (),
                 RequirementExpression::Ref(req) => req.remove_used_items(inventory, options, items),
             };
         }
     }
+}
+
+/// glitched tricks only count in glitched logic
+fn trick_enabled(options: &Options, trick: Trick) -> bool {
+    options.enabled_tricks.has(trick)
+        || (options.logic_mode == LogicMode::Glitched && options.enabled_tricks_glitched.has(trick))
+}
+
+/// where to continue after an instruction
+#[derive(Debug, Clone, Copy)]
+pub enum Next {
+    Jump(u16),
+    Done(bool),
+}
+
+/// the base requirements of `RequirementExpression`
+#[derive(Clone, Copy)]
+pub enum Test {
+    Item(Item, u8),
+    Event(Event),
+    Area(Area, TimeOfDay),
+    Fixed(bool),
+    Trick(Trick),
+    Option(fn(&Options) -> bool),
+}
 
-    pub fn owned(&self) -> RequirementExpression<'static> {
+impl Test {
+    pub fn check(&self, inventory: &Inventory, options: &Options, allowed_tod: TimeOfDay) -> bool {
         match self {
-            RequirementExpression::And(reqs) => {
-                RequirementExpression::And(reqs.iter().map(|req| req.owned()).collect())
-            }
-            RequirementExpression::Or(reqs) => {
-                RequirementExpression::Or(reqs.iter().map(|req| req.owned()).collect())
-            }
-            RequirementExpression::Item(item_id, count) => {
-                RequirementExpression::Item(*item_id, *count)
-            }
-            RequirementExpression::Event(event_id) => RequirementExpression::Event(*event_id),
-            RequirementExpression::Area(area, tod) => RequirementExpression::Area(*area, *tod),
-            RequirementExpression::Fixed(value) => RequirementExpression::Fixed(*value),
-            RequirementExpression::Option(f) => RequirementExpression::Option(*f),
-            RequirementExpression::Ref(inner) => inner.owned(),
+            Test::Item(item, count) => inventory.item_count(*item) >= *count,
+            Test::Event(event) => inventory.has_event(*event),
+            Test::Area(area, tod) => !inventory
+                .get_area_tod(*area)
+                .intersection(allowed_tod)
+                .intersection(*tod)
+                .is_empty(),
+            Test::Fixed(val) => *val,
+            Test::Trick(trick) => trick_enabled(options, *trick),
+            Test::Option(f) => f(options),
+        }
+    }
+}
+
+/// a requirement compiled by the dumper: every instruction tests a base requirement and then either jumps
+/// or finishes, depending on the result
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    pub test: Test,
+    pub if_true: Next,
+    pub if_false: Next,
+}
+
+/// evaluates a compiled requirement, starting at the first instruction
+/// jumps only go forward, so this always finishes
+pub fn check_bytecode(
+    code: &[Instruction],
+    inventory: &Inventory,
+    options: &Options,
+    allowed_tod: TimeOfDay,
+) -> bool {
+    let mut pos = 0;
+    loop {
+        let instruction = &code[pos];
+        let next = if instruction.test.check(inventory, options, allowed_tod) {
+            instruction.if_true
+        } else {
+            instruction.if_false
+        };
+        match next {
+            Next::Jump(target) => pos = target as usize,
+            Next::Done(result) => return result,
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RequirementKey {
     Exit(Exit),
     LogicExit { from: Area, to: Area },
@@ -362,6 +596,39 @@ pub enum RequirementKey {
     Event(Event),
 }
 
+impl RequirementKey {
+    /// the position in the generated requirement tables, which contain exits, logic exits,
+    /// locations and then events
+    pub fn index(self) -> usize {
+        match self {
+            Self::Exit(exit) => exit as usize,
+            Self::LogicExit { from, to } => {
+                let area = from.get();
+                let offset = area
+                    .logic_exits
+                    .iter()
+                    .position(|area| *area == to)
+                    .expect("logic exit doesn't exist");
+                area.logic_exit_index + offset
+            }
+            Self::Location(location) => Exit::ALL.len() + LOGIC_EXIT_COUNT + location as usize,
+            Self::Event(event) => {
+                Exit::ALL.len() + LOGIC_EXIT_COUNT + Location::ALL.len() + event as usize
+            }
+        }
+    }
+}
+
+impl From<RequirementKey> for usize {
+    fn from(val: RequirementKey) -> Self {
+        val.index()
+    }
+}
+
+impl BitSetCompatible for RequirementKey {
+    const ALL: &'static [RequirementKey] = &REQUIREMENT_KEYS;
+}
+
 impl From<Exit> for RequirementKey {
     fn from(value: Exit) -> Self {
         Self::Exit(value)
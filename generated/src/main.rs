@@ -1,35 +1,122 @@
-use std::{env::args, fmt::Write};
+use std::{env::args, fmt::Write, thread};
 
-use generated::get_logic;
+use generated::{get_logic, Options};
 
 use rand::{prelude::*, rngs::OsRng};
 
-use crate::assumed_fill::run_with_seed;
+use crate::{
+    assumed_fill::{run_with_retries, RetriedWorlds},
+    individual_world::{collect_spheres, write_spheres},
+};
 
 pub mod assumed_fill;
+pub mod batch;
+pub mod bench;
 pub mod constants;
+pub mod entrance_rando;
+pub mod explain;
 pub mod explorer;
 pub mod generated;
+pub mod hints;
 pub mod individual_world;
 pub mod logic_static;
 pub mod plando;
+pub mod playthrough;
 pub mod util;
 
 pub fn main() {
+    if args().nth(1).as_deref() == Some("bench") {
+        let rounds = args()
+            .nth(2)
+            .and_then(|arg| arg.parse().ok())
+            .unwrap_or(100);
+        bench::bench_requirements(rounds);
+        return;
+    }
+    // usage: batch <first seed> <seed count> [world count] [thread count] [max attempts] [csv <dir>]
+    //   [--options=<file>] [--<option>=<value>]...
+    // prints json statistics, unless a directory for csv files is given
+    // options are set by their command in options.yaml, like --logic-mode=Glitched
+    if args().nth(1).as_deref() == Some("batch") {
+        let (flags, positional): (Vec<String>, Vec<String>) =
+            args().skip(2).partition(|arg| arg.starts_with("--"));
+        let arg = |n: usize| positional.get(n).and_then(|arg| arg.parse().ok());
+        let first_seed: u64 = arg(0).unwrap_or(0);
+        let seed_count: u64 = arg(1).unwrap_or(100);
+        let world_count = arg(2).map_or(1, |count: u64| count as usize);
+        let threads = arg(3).map_or_else(
+            || thread::available_parallelism().map_or(1, |threads| threads.get()),
+            |threads: u64| threads as usize,
+        );
+        // only one attempt by default, so the failure rate shows how often the fill fails
+        let max_attempts = arg(4).map_or(1, |attempts: u64| attempts as u32);
+        let options = match batch::options_from_flags(flags.iter().map(String::as_str)) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+        let requirements = get_logic(&options);
+        let stats = batch::run_batch(
+            first_seed..first_seed + seed_count,
+            &options,
+            &requirements,
+            world_count,
+            threads,
+            max_attempts,
+        );
+        if positional.get(5).map(String::as_str) == Some("csv") {
+            let dir = positional.get(6).map_or(".", String::as_str);
+            stats.write_csv(dir.as_ref()).unwrap();
+        } else {
+            print!("{}", stats.to_json());
+        }
+        return;
+    }
+    // usage: [world count] [max attempts]
     let world_count = args().nth(1).and_then(|arg| arg.parse().ok()).unwrap_or(1);
+    let max_attempts = args().nth(2).and_then(|arg| arg.parse().ok()).unwrap_or(10);
 
     let seed: u64 = OsRng.gen();
     println!("seed: {seed}");
-    let requirements = get_logic();
-    match run_with_seed(seed, &requirements, world_count) {
-        Ok(worlds) => {
+    let options = Options::default();
+    let requirements = get_logic(&options);
+    match run_with_retries(seed, &options, &requirements, world_count, max_attempts) {
+        Ok(RetriedWorlds {
+            filled,
+            seed: attempt_seed,
+            attempts,
+            failed_attempts,
+        }) => {
+            for (attempt, e) in failed_attempts.iter().enumerate() {
+                eprintln!("attempt {}: {e}", attempt + 1);
+            }
+            let worlds = &filled.worlds;
             let mut out = String::new();
+            if attempts != 1 {
+                writeln!(
+                    &mut out,
+                    "attempts: {attempts}, attempt seed: {attempt_seed}"
+                )
+                .unwrap();
+            }
             for (world_id, world) in worlds.iter().enumerate() {
                 if worlds.len() != 1 {
                     writeln!(&mut out, "WORLD {world_id}").unwrap();
                 }
                 world.write_placement(&mut out).unwrap();
             }
+            writeln!(&mut out).unwrap();
+            let multiworld = worlds.len() != 1;
+            write_spheres(&mut out, "Spheres", &collect_spheres(worlds), multiworld).unwrap();
+            writeln!(&mut out).unwrap();
+            match filled.playthrough() {
+                Some(playthrough) => {
+                    write_spheres(&mut out, "Playthrough", playthrough, multiworld).unwrap()
+                }
+                None => writeln!(&mut out, "Playthrough: the goal can't be reached").unwrap(),
+            }
             print!("{out}");
         }
         Err(e) => {
@@ -5,7 +5,7 @@ use snafu::Snafu;
 
 use crate::{
     explorer::{MultiworldExplorer, Placement},
-    generated::{Item, Location, Options},
+    generated::{Item, Location, LogicMode, Options},
     logic_static::Requirements,
 };
 
@@ -19,6 +19,7 @@ use crate::{
 /// 3. rest
 ///
 /// then every entry gets processed, all possible item/location combinations are checked if the logic works and then chosen by the weight
+/// (without logic, every combination works)
 ///
 /// if the entry is not possible because another setting (non plando) occupies a locations (for example a key could be in the place of a locations where a sword is placed)
 /// or already placed an item (I can't think of a scenario here), then that's an error and we try again
@@ -48,6 +49,25 @@ pub enum Error {
     PlandoEntryConflict,
     #[snafu(display("No location item combination possible"))]
     PlandoNoLocation,
+    #[snafu(display(
+        "{item} is progress, but the banned types exclude all locations it can be at"
+    ))]
+    NoProgressLocation { item: &'static str },
+}
+
+impl Error {
+    /// if the error depends on the random order, so another seed can work
+    /// conflicts between settings and plando fail with every seed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::NoLocationLeft { .. } | Self::PlandoNoLocation => true,
+            Self::SettingsConflictLocation { .. }
+            | Self::SettingsConflictItem { .. }
+            | Self::PlandoSettingsConflict
+            | Self::PlandoEntryConflict
+            | Self::NoProgressLocation { .. } => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -212,6 +232,33 @@ pub fn place_items_plando(
 ) -> Result<(), Error> {
     let mut settings_placed_items: HashSet<Item> = HashSet::default();
     let mut settings_filled_locations: HashSet<Location> = HashSet::default();
+    // the locations of an entry and the banned types come from the settings,
+    // so this fails with every seed and has to be checked before anything can fail randomly
+    for entry in plando_entries.iter() {
+        let (entry_items, entry_locations) = match entry {
+            PlandoEntry::Fixed { item, location, .. } => (
+                vec![WeightedItem::simple(*item)],
+                vec![WeightedLocation::simple(*location)],
+            ),
+            PlandoEntry::Flex {
+                items, locations, ..
+            } => (items.clone(), locations.clone()),
+        };
+        let has_progress_location = entry_locations.iter().any(|place| {
+            place
+                .location
+                .is_start_or(|loc| progress_locations.contains(&loc))
+        });
+        for placable in entry_items.iter() {
+            if let ItemOrVacant::Item(item) = placable.item {
+                if progress_items.contains(&item) && !has_progress_location {
+                    return Err(Error::NoProgressLocation {
+                        item: item.get().name,
+                    });
+                }
+            }
+        }
+    }
     plando_entries.shuffle(rng);
     // this makes sure that entries with the same item * locations length are near another, but random
     fn rate_entry(entry: &PlandoEntry) -> usize {
@@ -344,7 +391,8 @@ pub fn place_items_plando(
                                     .location
                                     .is_start_or(|l| progress_locations.contains(&l)))
                                 && loc_or_start.location.is_start_or(|loc_to_fill| {
-                                    explorer.can_reach(world_index, loc_to_fill)
+                                    options.logic_mode == LogicMode::NoLogic
+                                        || explorer.can_reach(world_index, loc_to_fill)
                                 })
                             {
                                 possible_combinations.push(WeightedCombination {
@@ -0,0 +1,74 @@
+use crate::{
+    explorer::{MultiworldExplorer, Placement, SphereExplorer},
+    generated::{Event, Item, Location},
+    individual_world::{collect_spheres, SingleWorld},
+    plando::ItemOrVacant,
+};
+
+/// the spheres of (world, location, item)
+pub type Playthrough = Vec<Vec<(usize, Location, Item)>>;
+
+/// what every world has to reach to beat the game
+pub const GOAL: Event = Event::BeatDemise;
+
+/// if every world can reach the goal with the given placements
+fn can_reach_goal(worlds: &[SingleWorld<'_>], placements: &[Placement]) -> bool {
+    let mut explorer = MultiworldExplorer::new();
+    for (world, placement) in worlds.iter().zip(placements.iter()) {
+        explorer.add_world(
+            world.world_id,
+            None,
+            placement,
+            &world.world_requirements,
+            &world.options,
+        );
+    }
+    worlds
+        .iter()
+        .all(|world| explorer.can_reach(world.world_id, GOAL))
+}
+
+/// the spheres, reduced to the items needed to reach the goal in every world
+/// starting with the last sphere, every item is removed if the goal can still be reached without it
+/// `None` if the goal can't be reached at all
+pub fn minimal_playthrough(worlds: &[SingleWorld<'_>]) -> Option<Playthrough> {
+    let mut placements: Vec<Placement> =
+        worlds.iter().map(|world| world.placement.clone()).collect();
+    if !can_reach_goal(worlds, &placements) {
+        return None;
+    }
+    let world_index = |world_id: usize| {
+        worlds
+            .iter()
+            .position(|world| world.world_id == world_id)
+            .unwrap()
+    };
+
+    for (world, location, _) in collect_spheres(worlds).iter().flatten().rev() {
+        let placement = &mut placements[world_index(*world)];
+        let Some((item_world, item)) = placement.get_item_at(*location) else {
+            continue;
+        };
+        placement
+            .locations
+            .insert(*location, (item_world, ItemOrVacant::Vacant));
+        if !can_reach_goal(worlds, &placements) {
+            // needed, put it back
+            placements[world_index(*world)]
+                .locations
+                .insert(*location, (item_world, item));
+        }
+    }
+
+    // the spheres of only the required items
+    let mut explorer = SphereExplorer::new();
+    for (world, placement) in worlds.iter().zip(placements.iter()) {
+        explorer.add_world(
+            world.world_id,
+            placement,
+            &world.world_requirements,
+            &world.options,
+        );
+    }
+    Some(explorer.collect_spheres())
+}
@@ -2,9 +2,12 @@ use std::collections::HashMap;
 
 use crate::loader::BooleanOption;
 use crate::loader::IntOption;
+use crate::loader::MultichoiceOption;
 use crate::loader::OptionEntry;
 use crate::loader::OptionVariant;
 use crate::loader::SinglechoiceOption;
+use crate::loader::GLITCHED_TRICK_OPTION;
+use crate::loader::TRICK_OPTION;
 use crate::requirements::RequirementExpression;
 use crate::structure::ConnectionShuffleType;
 use crate::structure::ContextLoadable;
@@ -14,6 +17,7 @@ use crate::structure::LogicContext;
 use crate::structure::RequirementKey;
 use crate::structure::TimeOfDay;
 
+use anyhow::{bail, Context};
 use heck::ToPascalCase;
 use heck::ToSnekCase;
 use heck::ToUpperCamelCase;
@@ -35,16 +39,49 @@ pub fn convert_to_upper_camel_case(s: &str) -> String {
     result
 }
 
+fn requirement_key_name(ctx: &LogicContext, key: &RequirementKey) -> String {
+    match key {
+        RequirementKey::Exit(exit) => format!("exit {}", exit.name(ctx)),
+        RequirementKey::LogicExit { from, to } => format!(
+            "logic exit {} -> {}",
+            from.ctx(ctx).full_name,
+            to.ctx(ctx).full_name
+        ),
+        RequirementKey::Location(location) => format!("location {}", location.name(ctx)),
+        RequirementKey::Event(event) => format!("event {}", event.name(ctx)),
+    }
+}
+
+/// the items, events and areas a requirement mentions
+fn collect_dependencies<'r>(
+    requirement: &'r RequirementExpression<'r>,
+    dependencies: &mut Vec<&'r RequirementExpression<'r>>,
+) {
+    match requirement {
+        RequirementExpression::And(exprs) | RequirementExpression::Or(exprs) => {
+            for expr in exprs {
+                collect_dependencies(expr, dependencies);
+            }
+        }
+        RequirementExpression::Not(expr) => collect_dependencies(expr, dependencies),
+        RequirementExpression::Ref(expr) => collect_dependencies(expr, dependencies),
+        RequirementExpression::Item(..)
+        | RequirementExpression::Event(_)
+        | RequirementExpression::Area(..) => dependencies.push(requirement),
+        _ => (),
+    }
+}
+
 pub fn dump(
     ctx: &LogicContext,
     requirements: &HashMap<RequirementKey, RequirementExpression<'static>>,
+    glitched_requirements: &HashMap<RequirementKey, RequirementExpression<'static>>,
     options: &Vec<OptionEntry>,
 ) -> anyhow::Result<String> {
     let mut out_stream = TokenStream::new();
     out_stream.extend(quote!(
         #![allow(non_camel_case_types)]
-        use std::collections::HashMap;
-        use super::logic_static::{TimeOfDay, ForceToD, BitSetCompatible, Requirements, RequirementExpression, RequirementKey};
+        use super::logic_static::{TimeOfDay, ForceToD, BitSet, BitSetCompatible, Requirements, RequirementExpression, ExpressionList, RequirementKey, RequirementTables, TrickBitset, Instruction, Next, Test, SetOptionError, split_choices};
     ));
     // dump the region enum
     let regions: Vec<_> = ctx
@@ -77,9 +114,9 @@ pub fn dump(
             pub areas: &'static [Area],
         }
 
-        impl Into<usize> for Region {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Region> for usize {
+            fn from(val: Region) -> Self {
+                val as usize
             }
         }
 
@@ -136,9 +173,9 @@ pub fn dump(
             pub areas: &'static [Area],
         }
 
-        impl Into<usize> for Stage {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Stage> for usize {
+            fn from(val: Stage) -> Self {
+                val as usize
             }
         }
 
@@ -166,6 +203,13 @@ pub fn dump(
     ));
 
     // // dump areas
+    // the dense requirement index of the first logic exit of each area, see the logic tables below
+    let mut logic_exit_indices = Vec::new();
+    let mut logic_exit_index = ctx.exits.len();
+    for area in ctx.areas.iter() {
+        logic_exit_indices.push(logic_exit_index);
+        logic_exit_index += area.logic_exits.len();
+    }
     let area_idents: Vec<_> = ctx
         .areas
         .iter()
@@ -203,6 +247,7 @@ pub fn dump(
             .logic_entrances
             .iter()
             .map(|x| Ident::new(&x.ctx(ctx).ident, Span::call_site()));
+        let logic_exit_index = logic_exit_indices[a.id.0 as usize];
 
         quote!(
             Area::#ident => &AreaDef {
@@ -217,6 +262,7 @@ pub fn dump(
                 map_entrances: &[#(Entrance::#map_entrances,)*],
                 logic_exits: &[#(Area::#logic_exits,)*],
                 logic_entrances: &[#(Area::#logic_entrances,)*],
+                logic_exit_index: #logic_exit_index,
             },
         )
     });
@@ -238,11 +284,12 @@ pub fn dump(
             pub map_entrances: &'static [Entrance],
             pub logic_exits: &'static [Area],
             pub logic_entrances: &'static [Area],
+            pub logic_exit_index: usize,
         }
 
-        impl Into<usize> for Area {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Area> for usize {
+            fn from(val: Area) -> Self {
+                val as usize
             }
         }
 
@@ -307,6 +354,8 @@ pub fn dump(
         let connection_shuffle_type = match e.connection_shuffle_type {
             ConnectionShuffleType::Never => quote!(ConnectionShuffleType::Never),
             ConnectionShuffleType::Other => quote!(ConnectionShuffleType::Other),
+            ConnectionShuffleType::TrialGate => quote!(ConnectionShuffleType::TrialGate),
+            ConnectionShuffleType::TrialExit => quote!(ConnectionShuffleType::TrialExit),
         };
         let exit_patch_info = e.patch_info.iter().map(|patch_info| {
             let stage_name = &patch_info.stage_name;
@@ -349,6 +398,8 @@ pub fn dump(
         pub enum ConnectionShuffleType {
             Never,
             Other,
+            TrialGate,
+            TrialExit,
         }
 
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -428,9 +479,9 @@ pub fn dump(
             #(#exit_idents,)*
         }
 
-        impl Into<usize> for Exit {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Exit> for usize {
+            fn from(val: Exit) -> Self {
+                val as usize
             }
         }
 
@@ -514,9 +565,9 @@ pub fn dump(
             #(#entrance_idents,)*
         }
 
-        impl Into<usize> for Entrance {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Entrance> for usize {
+            fn from(val: Entrance) -> Self {
+                val as usize
             }
         }
 
@@ -561,9 +612,9 @@ pub fn dump(
             #(#event_idents,)*
         }
 
-        impl Into<usize> for Event {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Event> for usize {
+            fn from(val: Event) -> Self {
+                val as usize
             }
         }
 
@@ -586,7 +637,8 @@ pub fn dump(
     item_values.sort_unstable_by_key(|item| item.id);
 
     // dump items
-    let item_idents: Vec<_> = item_values.iter()
+    let item_idents: Vec<_> = item_values
+        .iter()
         .map(|item| Ident::new(&item.ident, Span::call_site()))
         .collect();
     let item_cases = item_values.iter().map(|item| {
@@ -610,9 +662,9 @@ pub fn dump(
             #(#item_idents,)*
         }
 
-        impl Into<usize> for Item {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Item> for usize {
+            fn from(val: Item) -> Self {
+                val as usize
             }
         }
 
@@ -631,6 +683,51 @@ pub fn dump(
         }
     ));
 
+    // dump tricks
+    let trick_idents: Vec<_> = ctx
+        .tricks
+        .iter()
+        .map(|trick| Ident::new(&trick.ident, Span::call_site()))
+        .collect();
+    let trick_cases = ctx.tricks.iter().map(|trick| {
+        let name = &trick.name;
+        let ident = Ident::new(&trick.ident, Span::call_site());
+        quote!(Trick::#ident => &TrickDef {
+            name: #name
+        },)
+    });
+
+    out_stream.extend(quote!(
+        pub struct TrickDef {
+            pub name: &'static str,
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Trick {
+            #(#trick_idents,)*
+        }
+
+        impl From<Trick> for usize {
+            fn from(val: Trick) -> Self {
+                val as usize
+            }
+        }
+
+        impl BitSetCompatible for Trick {
+            const ALL: &'static [Trick] = &[
+                #(Trick::#trick_idents,)*
+            ];
+        }
+
+        impl Trick {
+            pub fn get(&self) -> &'static TrickDef {
+                match self {
+                    #(#trick_cases)*
+                }
+            }
+        }
+    ));
+
     // dump locations
     let location_idents: Vec<_> = ctx
         .locations
@@ -660,11 +757,16 @@ pub fn dump(
                 quote!(LocationKind::GossipStone { text_path: #text_path })
             }
         };
+        let types = loc
+            .types
+            .iter()
+            .map(|t| Ident::new(&convert_to_upper_camel_case(t), Span::call_site()));
         quote!(Location::#ident => &LocationDef {
             name: #name,
             area: Area::#area,
             display_name: #display_name,
             kind: #kind,
+            types: &[#(BannedTypes::#types,)*],
         },)
     });
 
@@ -683,6 +785,7 @@ pub fn dump(
             pub area: Area,
             pub display_name: &'static str,
             pub kind: LocationKind,
+            pub types: &'static [BannedTypes],
         }
 
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -690,9 +793,9 @@ pub fn dump(
             #(#location_idents,)*
         }
 
-        impl Into<usize> for Location {
-            fn into(self) -> usize {
-                self as usize
+        impl From<Location> for usize {
+            fn from(val: Location) -> Self {
+                val as usize
             }
         }
 
@@ -711,41 +814,178 @@ pub fn dump(
         }
     ));
 
-    let mut sorted_reqs: Vec<_> = requirements.iter().collect();
-    sorted_reqs.sort_unstable_by_key(|(key, _)| *key);
-
     // dump logic
-    let logic_exprs = sorted_reqs
+    // every requirement gets a dense index: exits, then logic exits, then locations, then events
+    let mut dense_keys: Vec<RequirementKey> = ctx.exits.iter().map(|e| e.id.into()).collect();
+    for area in ctx.areas.iter() {
+        dense_keys.extend(area.logic_exits.iter().map(|to| RequirementKey::LogicExit {
+            from: area.id,
+            to: *to,
+        }));
+    }
+    dense_keys.extend(ctx.locations.iter().map(|l| RequirementKey::from(l.id)));
+    dense_keys.extend(ctx.events.iter().map(|e| RequirementKey::from(e.id)));
+    let logic_exit_count: usize = ctx.areas.iter().map(|a| a.logic_exits.len()).sum();
+    let key_count = dense_keys.len();
+
+    let key_exprs = dense_keys.iter().map(|key| match key {
+        RequirementKey::Exit(exit) => {
+            let exit_expr = Ident::new(&exit.ctx(ctx).ident, Span::call_site());
+            quote!(RequirementKey::Exit(Exit::#exit_expr))
+        }
+        RequirementKey::LogicExit { from, to } => {
+            let from_expr = Ident::new(&from.ctx(ctx).ident, Span::call_site());
+            let to_expr = Ident::new(&to.ctx(ctx).ident, Span::call_site());
+            quote!(RequirementKey::LogicExit{from: Area::#from_expr, to: Area::#to_expr})
+        }
+        RequirementKey::Location(location) => {
+            let location_expr = Ident::new(&location.ctx(ctx).ident, Span::call_site());
+            quote!(RequirementKey::Location(Location::#location_expr))
+        }
+        RequirementKey::Event(event) => {
+            let event_expr = Ident::new(&event.ctx(ctx).ident, Span::call_site());
+            quote!(RequirementKey::Event(Event::#event_expr))
+        }
+    });
+
+    // which requirements mention an item, event or area, by dense index
+    // so that exploring only has to check them again once one of those is found
+    let item_positions: HashMap<_, _> = item_values
         .iter()
-        .map(|(req_key, requirement)| {
-            let key_expr = match req_key {
-                RequirementKey::Exit(exit) => {
-                    let exit_expr = Ident::new(&exit.ctx(ctx).ident, Span::call_site());
-                    quote!(RequirementKey::Exit(Exit::#exit_expr))
-                }
-                RequirementKey::LogicExit { from, to } => {
-                    let from_expr = Ident::new(&from.ctx(ctx).ident, Span::call_site());
-                    let to_expr = Ident::new(&to.ctx(ctx).ident, Span::call_site());
-                    quote!(RequirementKey::LogicExit{from: Area::#from_expr, to: Area::#to_expr})
-                }
-                RequirementKey::Location(location) => {
-                    let location_expr = Ident::new(&location.ctx(ctx).ident, Span::call_site());
-                    quote!(RequirementKey::Location(Location::#location_expr))
-                }
-                RequirementKey::Event(event) => {
-                    let event_expr = Ident::new(&event.ctx(ctx).ident, Span::call_site());
-                    quote!(RequirementKey::Event(Event::#event_expr))
+        .enumerate()
+        .map(|(position, item)| (item.id, position))
+        .collect();
+    let dump_dependents = |glitched: bool| {
+        let mut item_dependents = vec![Vec::new(); item_values.len()];
+        let mut event_dependents = vec![Vec::new(); ctx.events.len()];
+        let mut area_dependents = vec![Vec::new(); ctx.areas.len()];
+        for (index, key) in dense_keys.iter().enumerate() {
+            let index = index as u16;
+            let requirement = glitched
+                .then(|| glitched_requirements.get(key))
+                .flatten()
+                .or_else(|| requirements.get(key));
+            let mut dependencies = Vec::new();
+            if let Some(requirement) = requirement {
+                collect_dependencies(requirement, &mut dependencies);
+            }
+            for dependency in dependencies {
+                let dependents = match dependency {
+                    RequirementExpression::Item(item, _) => {
+                        &mut item_dependents[item_positions[item]]
+                    }
+                    RequirementExpression::Event(event) => &mut event_dependents[event.0 as usize],
+                    RequirementExpression::Area(area, _) => &mut area_dependents[area.0 as usize],
+                    _ => unreachable!(),
+                };
+                if dependents.last() != Some(&index) {
+                    dependents.push(index);
                 }
-            };
+            }
+        }
+        let dump = |dependents: Vec<Vec<u16>>| {
+            let dependents = dependents
+                .into_iter()
+                .map(|indices| quote!(&[#(#indices),*]));
+            quote!([#(#dependents,)*])
+        };
+        (
+            dump(item_dependents),
+            dump(event_dependents),
+            dump(area_dependents),
+        )
+    };
+    let (item_dependents, event_dependents, area_dependents) = dump_dependents(false);
+    let (glitched_item_dependents, glitched_event_dependents, glitched_area_dependents) =
+        dump_dependents(true);
+
+    let mut logic_exprs = Vec::new();
+    let mut bytecode_exprs = Vec::new();
+    let mut glitched_logic_exprs = Vec::new();
+    let mut glitched_bytecode_exprs = Vec::new();
+    for (index, key) in dense_keys.iter().enumerate() {
+        let Some(requirement) = requirements.get(key) else {
+            bail!("no requirement for {}", requirement_key_name(ctx, key));
+        };
+        logic_exprs.push(requirement.dump(ctx));
+        bytecode_exprs.push(
+            requirement
+                .dump_bytecode(ctx)
+                .with_context(|| requirement_key_name(ctx, key))?,
+        );
+        // the glitched table only contains the requirements that differ from the bitless ones,
+        // the others point to the bitless table
+        if let Some(requirement) = glitched_requirements.get(key) {
             let req_expr = requirement.dump(ctx);
-            quote!((#key_expr, #req_expr))
-        });
+            glitched_logic_exprs.push(quote!(&#req_expr));
+            glitched_bytecode_exprs.push(
+                requirement
+                    .dump_bytecode(ctx)
+                    .with_context(|| format!("glitched {}", requirement_key_name(ctx, key)))?,
+            );
+        } else {
+            glitched_logic_exprs.push(quote!(BITLESS_REQUIREMENTS[#index]));
+            glitched_bytecode_exprs.push(quote!(BITLESS_BYTECODE[#index]));
+        }
+    }
+    if requirements.len() != key_count {
+        bail!("requirements exist for keys that aren't exits, logic exits, locations or events");
+    }
 
     out_stream.extend(quote!(
-        pub fn get_logic() -> Requirements<'static> {
-            Requirements::new_from_map(HashMap::from([
-                #(#logic_exprs,)*
-            ]))
+        pub const LOGIC_EXIT_COUNT: usize = #logic_exit_count;
+        pub const REQUIREMENT_KEY_COUNT: usize = #key_count;
+
+        pub static BITLESS_REQUIREMENTS: [&RequirementExpression; REQUIREMENT_KEY_COUNT] = [
+            #(&#logic_exprs,)*
+        ];
+
+        pub static BITLESS_BYTECODE: [&[Instruction]; REQUIREMENT_KEY_COUNT] = [
+            #(#bytecode_exprs,)*
+        ];
+
+        pub static GLITCHED_REQUIREMENTS: [&RequirementExpression; REQUIREMENT_KEY_COUNT] = [
+            #(#glitched_logic_exprs,)*
+        ];
+
+        pub static GLITCHED_BYTECODE: [&[Instruction]; REQUIREMENT_KEY_COUNT] = [
+            #(#glitched_bytecode_exprs,)*
+        ];
+
+        pub static REQUIREMENT_KEYS: [RequirementKey; REQUIREMENT_KEY_COUNT] = [
+            #(#key_exprs,)*
+        ];
+
+        pub static BITLESS_ITEM_DEPENDENTS: [&[u16]; Item::ALL.len()] = #item_dependents;
+        pub static BITLESS_EVENT_DEPENDENTS: [&[u16]; Event::ALL.len()] = #event_dependents;
+        pub static BITLESS_AREA_DEPENDENTS: [&[u16]; Area::ALL.len()] = #area_dependents;
+
+        pub static GLITCHED_ITEM_DEPENDENTS: [&[u16]; Item::ALL.len()] = #glitched_item_dependents;
+        pub static GLITCHED_EVENT_DEPENDENTS: [&[u16]; Event::ALL.len()] = #glitched_event_dependents;
+        pub static GLITCHED_AREA_DEPENDENTS: [&[u16]; Area::ALL.len()] = #glitched_area_dependents;
+
+        pub static BITLESS_LOGIC: RequirementTables = RequirementTables {
+            requirements: &BITLESS_REQUIREMENTS,
+            bytecode: &BITLESS_BYTECODE,
+            item_dependents: &BITLESS_ITEM_DEPENDENTS,
+            event_dependents: &BITLESS_EVENT_DEPENDENTS,
+            area_dependents: &BITLESS_AREA_DEPENDENTS,
+        };
+
+        pub static GLITCHED_LOGIC: RequirementTables = RequirementTables {
+            requirements: &GLITCHED_REQUIREMENTS,
+            bytecode: &GLITCHED_BYTECODE,
+            item_dependents: &GLITCHED_ITEM_DEPENDENTS,
+            event_dependents: &GLITCHED_EVENT_DEPENDENTS,
+            area_dependents: &GLITCHED_AREA_DEPENDENTS,
+        };
+
+        pub fn get_logic(options: &Options) -> Requirements<'static> {
+            if options.logic_mode == LogicMode::Glitched {
+                Requirements::new_from_tables(GLITCHED_LOGIC)
+            } else {
+                Requirements::new_from_tables(BITLESS_LOGIC)
+            }
         }
     ));
 
@@ -753,24 +993,43 @@ pub fn dump(
     let mut option_fields = TokenStream::new();
     let mut default_instances = TokenStream::new();
     let mut variant_structs = TokenStream::new();
+    let mut setter_arms = TokenStream::new();
     for opt in options.iter().filter(|opt| opt.permalink) {
         let field_name = Ident::new(&opt.command.to_snek_case(), Span::call_site());
+        let command = &opt.command;
         match &opt.variant {
             OptionVariant::Boolean(BooleanOption { default }) => {
                 option_fields.extend(quote!(pub #field_name: bool,));
                 default_instances.extend(quote!(#field_name: #default,));
+                setter_arms.extend(quote!(
+                    #command => self.#field_name = value.parse().map_err(|_| invalid())?,
+                ));
             }
-            OptionVariant::Int(IntOption { default, .. }) => {
+            OptionVariant::Int(IntOption { default, min, max }) => {
                 option_fields.extend(quote!(pub #field_name: usize,));
                 default_instances.extend(quote!(#field_name: #default as usize,));
+                let min = min.unwrap_or(0).max(0) as usize;
+                let max = max.map_or(usize::MAX, |max| max as usize);
+                setter_arms.extend(quote!(
+                    #command => {
+                        self.#field_name = value
+                            .parse()
+                            .ok()
+                            .filter(|value| (#min..=#max).contains(value))
+                            .ok_or_else(invalid)?
+                    }
+                ));
             }
             OptionVariant::Singlechoice(SinglechoiceOption { default, choices }) => {
                 let choice_enum_name = Ident::new(&opt.command.to_pascal_case(), Span::call_site());
                 let default_ident =
                     Ident::new(&convert_to_upper_camel_case(&default), Span::call_site());
-                let variants = choices.iter().map(|choice| {
-                    Ident::new(&convert_to_upper_camel_case(choice), Span::call_site())
-                });
+                let variants: Vec<_> = choices
+                    .iter()
+                    .map(|choice| {
+                        Ident::new(&convert_to_upper_camel_case(choice), Span::call_site())
+                    })
+                    .collect();
                 variant_structs.extend(quote!(
                     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
                     pub enum #choice_enum_name {
@@ -779,6 +1038,107 @@ pub fn dump(
                 ));
                 option_fields.extend(quote!(pub #field_name: #choice_enum_name,));
                 default_instances.extend(quote!(#field_name: #choice_enum_name::#default_ident,));
+                setter_arms.extend(quote!(
+                    #command => {
+                        self.#field_name = match value {
+                            #(#choices => #choice_enum_name::#variants,)*
+                            _ => return Err(invalid()),
+                        }
+                    }
+                ));
+            }
+            OptionVariant::Multichoice(MultichoiceOption { default, .. })
+                if opt.command == TRICK_OPTION || opt.command == GLITCHED_TRICK_OPTION =>
+            {
+                let field_name = if opt.command == TRICK_OPTION {
+                    Ident::new("enabled_tricks", Span::call_site())
+                } else {
+                    field_name
+                };
+                let default_tricks = default.iter().map(|trick| {
+                    Ident::new(&convert_to_upper_camel_case(trick), Span::call_site())
+                });
+                option_fields.extend(quote!(pub #field_name: TrickBitset,));
+                default_instances.extend(
+                    quote!(#field_name: [#(Trick::#default_tricks,)*].into_iter().collect(),),
+                );
+                setter_arms.extend(quote!(
+                    #command => {
+                        self.#field_name = split_choices(value)
+                            .map(|choice| {
+                                Trick::ALL
+                                    .iter()
+                                    .find(|trick| trick.get().name == choice)
+                                    .copied()
+                                    .ok_or_else(invalid)
+                            })
+                            .collect::<Result<_, _>>()?
+                    }
+                ));
+            }
+            OptionVariant::Multichoice(MultichoiceOption { default, choices }) => {
+                let choice_enum_name = Ident::new(&opt.command.to_pascal_case(), Span::call_site());
+                let bitset_name = Ident::new(
+                    &format!("{}Bitset", opt.command.to_pascal_case()),
+                    Span::call_site(),
+                );
+                if let Some(unknown) = default.iter().find(|d| !choices.contains(d)) {
+                    bail!("default {unknown} of {} is not a choice", opt.command);
+                }
+                let variants: Vec<_> = choices
+                    .iter()
+                    .map(|choice| {
+                        Ident::new(&convert_to_upper_camel_case(choice), Span::call_site())
+                    })
+                    .collect();
+                let default_idents = default.iter().map(|choice| {
+                    Ident::new(&convert_to_upper_camel_case(choice), Span::call_site())
+                });
+                variant_structs.extend(quote!(
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+                    pub enum #choice_enum_name {
+                        #(#variants,)*
+                    }
+
+                    impl From<#choice_enum_name> for usize {
+                        fn from(val: #choice_enum_name) -> Self {
+                            val as usize
+                        }
+                    }
+
+                    impl BitSetCompatible for #choice_enum_name {
+                        const ALL: &'static [#choice_enum_name] = &[
+                            #(#choice_enum_name::#variants,)*
+                        ];
+                    }
+
+                    impl #choice_enum_name {
+                        pub fn name(&self) -> &'static str {
+                            match self {
+                                #(#choice_enum_name::#variants => #choices,)*
+                            }
+                        }
+                    }
+
+                    pub type #bitset_name = BitSet<#choice_enum_name, { #choice_enum_name::ALL.len() / usize::BITS as usize + 1 }>;
+                ));
+                option_fields.extend(quote!(pub #field_name: #bitset_name,));
+                default_instances.extend(quote!(
+                    #field_name: [#(#choice_enum_name::#default_idents,)*].into_iter().collect(),
+                ));
+                setter_arms.extend(quote!(
+                    #command => {
+                        self.#field_name = split_choices(value)
+                            .map(|choice| {
+                                #choice_enum_name::ALL
+                                    .iter()
+                                    .find(|variant| variant.name() == choice)
+                                    .copied()
+                                    .ok_or_else(invalid)
+                            })
+                            .collect::<Result<_, _>>()?
+                    }
+                ));
             }
             OptionVariant::Other => {
                 // ignore for now
@@ -800,6 +1160,26 @@ pub fn dump(
                 }
             }
         }
+
+        impl Options {
+            /// sets an option by its command in options.yaml, choices are given by their name
+            /// and multichoice options take a comma separated list
+            pub fn set(&mut self, command: &str, value: &str) -> Result<(), SetOptionError> {
+                let invalid = || SetOptionError::InvalidValue {
+                    command: command.to_string(),
+                    value: value.to_string(),
+                };
+                match command {
+                    #setter_arms
+                    _ => {
+                        return Err(SetOptionError::UnknownOption {
+                            command: command.to_string(),
+                        })
+                    }
+                }
+                Ok(())
+            }
+        }
     ));
 
     // println!("{}", out_stream.to_string());
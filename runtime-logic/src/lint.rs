@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use anyhow::Context;
+use indexmap::IndexMap;
+
+use crate::{
+    loader::{
+        load_logic, multichoice_choices, AreaYaml, LogicSource, RegionYaml, GLITCHED_TRICK_OPTION,
+        TRICK_OPTION,
+    },
+    requirements::{base_requirements, RequirementExpression},
+    structure::{
+        AreaId, ConnectionShuffleType, ContextLoadable, EventId, LogicContext, RequirementKey,
+    },
+};
+
+/// the entrance a new file starts at
+const START_ENTRANCE: &str = "KnightAcademy_From_Skyloft_Lower_Left";
+/// the events the randomizer sets from the start
+const START_EVENTS: &[&str] = &[
+    "Sealed Grounds Statue",
+    "Eldin Entrance Statue",
+    "Lanayru Mine Entry Statue",
+];
+/// the events the randomizer requires itself, instead of the logic
+const RANDOMIZER_REQUIRED_EVENTS: &[&str] = &[
+    "Beat Demise",
+    "Can Beat Skyview",
+    "Can Beat Earth Temple",
+    "Can Beat Lanayru Mining Facility",
+    "Can Beat Ancient Cistern",
+    "Can Beat Sandship",
+    "Can Beat Fire Sanctuary",
+    "Can Beat Sky Keep",
+];
+
+/// checks the logic files for mistakes that still generate valid code,
+/// returns a message for every problem found
+pub fn lint(source: &LogicSource, glitched: &LogicSource) -> anyhow::Result<Vec<String>> {
+    let mut findings = Vec::new();
+
+    let macros = source.read_macros()?;
+    let glitched_macros = glitched.read_overlay_macros()?;
+    let (regions, _) = source.read_regions()?;
+    let (glitched_regions, _) = glitched.read_overlay_regions()?;
+    let checks = source.read_checks()?;
+    let options = source.read_options()?;
+
+    lint_macros(
+        &[(&macros, &regions), (&glitched_macros, &glitched_regions)],
+        &mut findings,
+    )?;
+    lint_checks(&regions, &checks, &mut findings);
+    let bitless_tricks = multichoice_choices(&options, TRICK_OPTION)?;
+    let glitched_tricks = multichoice_choices(&options, GLITCHED_TRICK_OPTION)?;
+    lint_tricks(
+        &macros,
+        &regions,
+        bitless_tricks,
+        TRICK_OPTION,
+        &mut findings,
+    )?;
+    lint_tricks(
+        &glitched_macros,
+        &glitched_regions,
+        // glitched logic can use all tricks
+        &[bitless_tricks, glitched_tricks].concat(),
+        GLITCHED_TRICK_OPTION,
+        &mut findings,
+    )?;
+
+    // everything else needs the parsed logic
+    let (ctx, requirements, glitched_requirements, _) = match load_logic(source, glitched) {
+        Ok(logic) => logic,
+        Err(e) => {
+            findings.push(format!("logic can't be loaded: {e:#}"));
+            return Ok(findings);
+        }
+    };
+
+    lint_events(
+        &ctx,
+        requirements.values().chain(glitched_requirements.values()),
+        &mut findings,
+    );
+    lint_reachability(&ctx, &requirements, &mut findings)?;
+    lint_entrance_table(&ctx, &mut findings);
+
+    Ok(findings)
+}
+
+fn area_requirements(area: &AreaYaml) -> impl Iterator<Item = &String> {
+    area.locations
+        .values()
+        .chain(area.events.values())
+        .chain(area.map_exits.values())
+        .chain(area.logic_exits.values())
+        .chain(area.macros.values())
+}
+
+fn all_areas(
+    regions: &BTreeMap<String, RegionYaml>,
+) -> impl Iterator<Item = (&String, &String, &String, &AreaYaml)> {
+    regions.iter().flat_map(|(region_name, region)| {
+        region.stages.iter().flat_map(move |(stage_name, stage)| {
+            stage
+                .areas
+                .iter()
+                .map(move |(area_name, area)| (region_name, stage_name, area_name, area))
+        })
+    })
+}
+
+/// the macros and regions read from one logic directory
+type LogicDir<'a> = (
+    &'a IndexMap<String, String>,
+    &'a BTreeMap<String, RegionYaml>,
+);
+
+/// global macros have to be used anywhere, area macros in their own area
+fn lint_macros(logic_dirs: &[LogicDir], findings: &mut Vec<String>) -> anyhow::Result<()> {
+    let mut used = HashSet::new();
+    for (macros, regions) in logic_dirs {
+        for req in macros.values() {
+            used.extend(base_requirements(req)?);
+        }
+        for (_, _, _, area) in all_areas(regions) {
+            for req in area_requirements(area) {
+                used.extend(base_requirements(req)?);
+            }
+        }
+    }
+    for (macros, regions) in logic_dirs {
+        for name in macros.keys() {
+            if !used.contains(name.as_str()) {
+                findings.push(format!("macro {name} is never used"));
+            }
+        }
+        for (region_name, stage_name, area_name, area) in all_areas(regions) {
+            let mut used_in_area = HashSet::new();
+            for req in area_requirements(area) {
+                used_in_area.extend(base_requirements(req)?);
+            }
+            for name in area.macros.keys() {
+                if !used_in_area.contains(name.as_str()) {
+                    findings.push(format!(
+                        "macro {name} in {region_name}/{stage_name}/{area_name} is never used"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// tricks that aren't options can never be enabled, so they are never in logic
+fn lint_tricks(
+    macros: &IndexMap<String, String>,
+    regions: &BTreeMap<String, RegionYaml>,
+    tricks: &[String],
+    option: &str,
+    findings: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let mut unknown = BTreeSet::new();
+    let reqs = macros
+        .values()
+        .chain(all_areas(regions).flat_map(|(_, _, _, area)| area_requirements(area)));
+    for req in reqs {
+        for atom in base_requirements(req)? {
+            if let Some(trick) = atom.strip_suffix(" Trick") {
+                if !tricks.iter().any(|t| t == trick) {
+                    unknown.insert(trick);
+                }
+            }
+        }
+    }
+    for trick in unknown {
+        findings.push(format!("trick {trick} is not in {option}"));
+    }
+    Ok(())
+}
+
+fn lint_checks<T>(
+    regions: &BTreeMap<String, RegionYaml>,
+    checks: &IndexMap<String, T>,
+    findings: &mut Vec<String>,
+) {
+    let mut logic_locations = HashSet::new();
+    for (region_name, _, _, area) in all_areas(regions) {
+        for location_name in area.locations.keys() {
+            let display_name = format!("{region_name} - {location_name}");
+            if !checks.contains_key(&display_name) && !display_name.contains("Gossip Stone") {
+                findings.push(format!("location {display_name} is not in checks.yaml"));
+            }
+            logic_locations.insert(display_name);
+        }
+    }
+    for check_name in checks.keys() {
+        if !logic_locations.contains(check_name) {
+            findings.push(format!("check {check_name} has no logic"));
+        }
+    }
+}
+
+fn collect_events(req: &RequirementExpression, events: &mut HashSet<EventId>) {
+    match req {
+        RequirementExpression::And(reqs) | RequirementExpression::Or(reqs) => {
+            for req in reqs {
+                collect_events(req, events);
+            }
+        }
+        RequirementExpression::Event(event) => {
+            events.insert(*event);
+        }
+        RequirementExpression::Not(req) => collect_events(req, events),
+        RequirementExpression::Ref(req) => collect_events(req, events),
+        _ => (),
+    }
+}
+
+fn lint_events<'a>(
+    ctx: &LogicContext,
+    requirements: impl Iterator<Item = &'a RequirementExpression<'static>>,
+    findings: &mut Vec<String>,
+) {
+    let mut required_events = HashSet::new();
+    for req in requirements {
+        collect_events(req, &mut required_events);
+    }
+    for event in &ctx.events {
+        if !required_events.contains(&event.id)
+            && !RANDOMIZER_REQUIRED_EVENTS.contains(&event.name.as_str())
+        {
+            findings.push(format!("event {} is never required", event.name));
+        }
+    }
+}
+
+/// if a requirement could be met with all items, tricks and any options
+/// negations only contain options and tricks, so they are always considered met
+fn can_meet(
+    req: &RequirementExpression,
+    reached_areas: &HashSet<AreaId>,
+    events: &HashSet<EventId>,
+) -> bool {
+    match req {
+        RequirementExpression::And(reqs) => {
+            reqs.iter().all(|req| can_meet(req, reached_areas, events))
+        }
+        RequirementExpression::Or(reqs) => {
+            reqs.iter().any(|req| can_meet(req, reached_areas, events))
+        }
+        RequirementExpression::Event(event) => events.contains(event),
+        RequirementExpression::Area(area, _) => reached_areas.contains(area),
+        RequirementExpression::Fixed(value) => *value,
+        RequirementExpression::Ref(req) => can_meet(req, reached_areas, events),
+        RequirementExpression::Item(..)
+        | RequirementExpression::Trick(_)
+        | RequirementExpression::Not(_)
+        | RequirementExpression::OptionEnabled { .. }
+        | RequirementExpression::OptionIs { .. }
+        | RequirementExpression::OptionContains { .. }
+        | RequirementExpression::OptionCompare { .. } => true,
+    }
+}
+
+fn lint_reachability(
+    ctx: &LogicContext,
+    requirements: &HashMap<RequirementKey, RequirementExpression<'static>>,
+    findings: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let start = ctx
+        .entrance
+        .iter()
+        .find(|entrance| entrance.ident == START_ENTRANCE)
+        .with_context(|| format!("start entrance {START_ENTRANCE} not found"))?;
+
+    let mut reached_areas = HashSet::from([start.area]);
+    let mut events = HashSet::new();
+    for event_name in START_EVENTS {
+        events.insert(
+            ctx.find::<EventId>(event_name)
+                .with_context(|| format!("start event {event_name} not found"))?,
+        );
+    }
+    // requirements include their own area, so they can be checked without looking at the source
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (req_key, req) in requirements {
+            match req_key {
+                RequirementKey::Exit(exit) => {
+                    if let Some(entrance) = exit.ctx(ctx).vanilla_entrance {
+                        let area = entrance.ctx(ctx).area;
+                        if !reached_areas.contains(&area) && can_meet(req, &reached_areas, &events)
+                        {
+                            reached_areas.insert(area);
+                            changed = true;
+                        }
+                    }
+                }
+                RequirementKey::LogicExit { to, .. } => {
+                    if !reached_areas.contains(to) && can_meet(req, &reached_areas, &events) {
+                        reached_areas.insert(*to);
+                        changed = true;
+                    }
+                }
+                RequirementKey::Event(event) => {
+                    if !events.contains(event) && can_meet(req, &reached_areas, &events) {
+                        events.insert(*event);
+                        changed = true;
+                    }
+                }
+                RequirementKey::Location(_) => (),
+            }
+        }
+    }
+
+    for area in &ctx.areas {
+        if !reached_areas.contains(&area.id) {
+            findings.push(format!(
+                "area {} in {} can't be reached with all items",
+                area.full_name,
+                area.region.name(ctx)
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn lint_entrance_table(ctx: &LogicContext, findings: &mut Vec<String>) {
+    for exit in &ctx.exits {
+        if exit.connection_shuffle_type == ConnectionShuffleType::Never {
+            findings.push(format!(
+                "exit {} is not in entrance_table2.yaml",
+                exit.display_name
+            ));
+        }
+    }
+}
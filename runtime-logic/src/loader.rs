@@ -1,21 +1,24 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    fs::File,
+    fs::{self, File},
     io::BufReader,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{bail, Context};
 use indexmap::IndexMap;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::{
-    dumper::convert_to_upper_camel_case, requirements::RequirementExpression, structure::{
+    dumper::convert_to_upper_camel_case,
+    requirements::{RequirementExpression, RequirementSource},
+    structure::{
         self, Area, AreaId, ConnectionShuffleType, ContextLoadable, DoorConnection, DoubleDoor,
         Entrance, EntranceId, EntrancePatchInfo, Event, EventId, Exit, ExitId, ExitPatchInfo, Item,
         ItemId, Location, LocationId, LocationKind, LogicContext, Region, RegionId, RequirementKey,
-        Stage, StageId,
-    }
+        Stage, StageId, Trick, TrickId,
+    },
 };
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -27,36 +30,36 @@ pub enum TimeOfDay {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct AreaYaml {
-    force_tod: Option<TimeOfDay>,
+    pub force_tod: Option<TimeOfDay>,
     #[serde(default)]
-    locations: BTreeMap<String, String>,
+    pub locations: BTreeMap<String, String>,
     #[serde(default)]
-    can_sleep: bool,
+    pub can_sleep: bool,
     #[serde(default)]
-    events: BTreeMap<String, String>,
+    pub events: BTreeMap<String, String>,
     #[serde(default)]
-    map_exits: BTreeMap<String, String>,
+    pub map_exits: BTreeMap<String, String>,
     #[serde(default)]
-    logic_exits: BTreeMap<String, String>,
+    pub logic_exits: BTreeMap<String, String>,
     #[serde(default)]
-    macros: IndexMap<String, String>,
+    pub macros: IndexMap<String, String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct StageYaml {
-    force_tod: Option<TimeOfDay>,
+    pub force_tod: Option<TimeOfDay>,
     // stage: String,
     #[serde(default)]
-    areas: BTreeMap<String, AreaYaml>,
+    pub areas: BTreeMap<String, AreaYaml>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct RegionYaml {
-    force_tod: Option<TimeOfDay>,
+    pub force_tod: Option<TimeOfDay>,
     #[serde(default)]
-    stages: BTreeMap<String, StageYaml>,
+    pub stages: BTreeMap<String, StageYaml>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,6 +73,15 @@ pub struct ItemYaml {
     //   type: Single
 }
 
+/// what kind of connection an entry of the entrance table is, plain ones don't have a type
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionTypeYaml {
+    Statue,
+    TrialGate,
+    TrialExit,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ConnectionYaml {
@@ -77,7 +89,11 @@ pub struct ConnectionYaml {
     to_stage: String,
     disambiguation: Option<String>,
     door: Option<DoubleDoor>,
-    orig: EntranceYaml,
+    #[serde(rename = "type")]
+    connection_type: Option<ConnectionTypeYaml>,
+    /// missing for connections that can't be patched yet
+    orig: Option<EntranceYaml>,
+    #[serde(default)]
     scens: Vec<ExitYaml>,
 }
 
@@ -135,6 +151,8 @@ pub enum OptionVariant {
     Int(IntOption),
     #[serde(rename = "singlechoice")]
     Singlechoice(SinglechoiceOption),
+    #[serde(rename = "multichoice")]
+    Multichoice(MultichoiceOption),
     #[serde(other)]
     Other,
 }
@@ -157,101 +175,294 @@ pub struct SinglechoiceOption {
     pub choices: Vec<String>,
 }
 
-fn read_logic_into(name: &str, map: &mut BTreeMap<String, RegionYaml>) -> anyhow::Result<()> {
-    let reader = BufReader::new(
-        File::open(format!("../bitless/{name}.yaml")).with_context(|| name.to_string())?,
-    );
-    let region: BTreeMap<String, RegionYaml> = serde_yaml::from_reader(reader)?;
-    map.extend(region);
-    Ok(())
+#[derive(Debug, Deserialize)]
+pub struct MultichoiceOption {
+    #[serde(default)]
+    pub default: Vec<String>,
+    pub choices: Vec<String>,
 }
 
-fn parse_macro_file() -> anyhow::Result<IndexMap<String, String>> {
-    let reader = BufReader::new(File::open(format!("../bitless/macros.yaml")).context("macros")?);
-    let raw_macros: IndexMap<String, String> = serde_yaml::from_reader(reader)?;
+pub fn multichoice_choices<'a>(
+    options: &'a [OptionEntry],
+    command: &str,
+) -> anyhow::Result<&'a [String]> {
+    options
+        .iter()
+        .find(|opt| opt.command == command)
+        .and_then(|opt| match &opt.variant {
+            OptionVariant::Multichoice(multichoice) => Some(multichoice.choices.as_slice()),
+            _ => None,
+        })
+        .with_context(|| format!("multichoice option {command} not found"))
+}
 
-    Ok(raw_macros)
+/// the multichoice option listing all tricks that can be referenced in logic
+pub const TRICK_OPTION: &str = "enabled-tricks-bitless";
+/// the multichoice option listing the tricks that can additionally be referenced in glitched logic
+pub const GLITCHED_TRICK_OPTION: &str = "enabled-tricks-glitched";
+/// the multichoice option listing all check types
+pub const BANNED_TYPES_OPTION: &str = "banned-types";
+
+/// the name of the macro file inside a logic directory, every other yaml file is a region file
+const MACRO_FILE_NAME: &str = "macros.yaml";
+
+/// locates the yaml files the logic is generated from: the shared files (items, checks,
+/// entrances, options) directly in the root, the region and macro files in a subdirectory
+/// per logic mode
+#[derive(Debug, Clone)]
+pub struct LogicSource {
+    root: PathBuf,
+    logic_dir: PathBuf,
 }
 
-pub fn read_item_file() -> anyhow::Result<HashMap<ItemId, Item>> {
-    let reader = BufReader::new(File::open(format!("../items.yaml")).context("items")?);
-    let items_yaml: Vec<ItemYaml> = serde_yaml::from_reader(reader)?;
+impl LogicSource {
+    pub fn new(root: impl Into<PathBuf>, logic_mode_dir: &str) -> Self {
+        let root = root.into();
+        let logic_dir = root.join(logic_mode_dir);
+        LogicSource { root, logic_dir }
+    }
 
-    let items = items_yaml
-        .into_iter()
-        .map(|item_yaml| {
-            let item_id = ItemId(item_yaml.id);
-            let item = Item {
-                id: item_id,
-                ident: convert_to_upper_camel_case(&item_yaml.name),
-                name: item_yaml.name,
-            };
-            (item_id, item)
-        })
-        .collect();
+    /// the same root with a different logic directory
+    pub fn overlay(&self, logic_mode_dir: &str) -> Self {
+        LogicSource::new(&self.root, logic_mode_dir)
+    }
 
-    Ok(items)
-}
+    /// a path for error messages, relative to the root
+    pub fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
 
-pub fn read_entrance_table() -> anyhow::Result<Vec<ConnectionYaml>> {
-    let reader =
-        BufReader::new(File::open(format!("../entrance_table2.yaml")).context("entrance_table")?);
-    let connections: Vec<ConnectionYaml> = serde_yaml::from_reader(reader)?;
+    fn read_yaml<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+        let reader = BufReader::new(
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+        );
+        serde_yaml::from_reader(reader).with_context(|| path.display().to_string())
+    }
 
-    Ok(connections)
-}
+    /// all region files in the logic directory, sorted by file name
+    pub fn region_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.logic_dir)
+            .with_context(|| format!("failed to read {}", self.logic_dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "yaml")
+                && path.file_name().is_some_and(|name| name != MACRO_FILE_NAME)
+            {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
 
-pub fn read_checks() -> anyhow::Result<IndexMap<String, CheckYaml>> {
-    let reader = BufReader::new(File::open(format!("../checks.yaml")).context("checks")?);
-    let result: IndexMap<String, CheckYaml> = serde_yaml::from_reader(reader)?;
+    /// reads all region files, also returns which file each region was read from
+    pub fn read_regions(
+        &self,
+    ) -> anyhow::Result<(BTreeMap<String, RegionYaml>, HashMap<String, String>)> {
+        let mut regions = BTreeMap::new();
+        let mut region_files = HashMap::new();
+        for path in self.region_files()? {
+            let file_regions: BTreeMap<String, RegionYaml> = Self::read_yaml(&path)?;
+            for region_name in file_regions.keys() {
+                if let Some(other) =
+                    region_files.insert(region_name.clone(), self.display_path(&path))
+                {
+                    bail!(
+                        "region {region_name} is defined in {other} and {}",
+                        self.display_path(&path)
+                    );
+                }
+            }
+            regions.extend(file_regions);
+        }
+        Ok((regions, region_files))
+    }
 
-    Ok(result)
-}
+    /// like `read_regions`, but an overlay doesn't need to exist
+    pub fn read_overlay_regions(
+        &self,
+    ) -> anyhow::Result<(BTreeMap<String, RegionYaml>, HashMap<String, String>)> {
+        if self.logic_dir.exists() {
+            self.read_regions()
+        } else {
+            Ok((BTreeMap::new(), HashMap::new()))
+        }
+    }
+
+    pub fn macro_file(&self) -> PathBuf {
+        self.logic_dir.join(MACRO_FILE_NAME)
+    }
+
+    pub fn read_macros(&self) -> anyhow::Result<IndexMap<String, String>> {
+        Self::read_yaml(&self.macro_file())
+    }
+
+    /// like `read_macros`, but an overlay doesn't need its own macros
+    pub fn read_overlay_macros(&self) -> anyhow::Result<IndexMap<String, String>> {
+        if self.macro_file().exists() {
+            self.read_macros()
+        } else {
+            Ok(IndexMap::new())
+        }
+    }
+
+    pub fn read_items(&self) -> anyhow::Result<HashMap<ItemId, Item>> {
+        let items_yaml: Vec<ItemYaml> = Self::read_yaml(&self.root.join("items.yaml"))?;
+
+        let items = items_yaml
+            .into_iter()
+            .map(|item_yaml| {
+                let item_id = ItemId(item_yaml.id);
+                let item = Item {
+                    id: item_id,
+                    ident: convert_to_upper_camel_case(&item_yaml.name),
+                    name: item_yaml.name,
+                };
+                (item_id, item)
+            })
+            .collect();
 
-pub fn read_options() -> anyhow::Result<Vec<OptionEntry>> {
-    let reader = BufReader::new(File::open(format!("../options.yaml")).context("options")?);
-    let result: Vec<OptionEntry> = serde_yaml::from_reader(reader)?;
+        Ok(items)
+    }
 
-    Ok(result)
+    pub fn read_entrance_table(&self) -> anyhow::Result<Vec<ConnectionYaml>> {
+        Self::read_yaml(&self.root.join("entrance_table2.yaml"))
+    }
+
+    pub fn read_checks(&self) -> anyhow::Result<IndexMap<String, CheckYaml>> {
+        Self::read_yaml(&self.root.join("checks.yaml"))
+    }
+
+    pub fn read_options(&self) -> anyhow::Result<Vec<OptionEntry>> {
+        Self::read_yaml(&self.root.join("options.yaml"))
+    }
 }
 
-pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, RequirementExpression<'static>>, Vec<OptionEntry>)> {
-    let macros = parse_macro_file()?;
-    let items = read_item_file()?;
-    let checks = read_checks()?;
-    let options = read_options()?;
+/// requirements of a logic mode that are OR-ed onto the base logic
+#[derive(Default)]
+struct LogicOverlay<'a> {
+    macros: IndexMap<String, String>,
+    area_macros: HashMap<AreaId, &'a IndexMap<String, String>>,
+    requirements: HashMap<RequirementKey, Vec<(AreaId, &'a str, &'a str)>>,
+}
+
+/// parses macros in order, so that they can reference the ones defined before them
+/// an overlay macro is OR-ed onto the base macro with the same name, or added after all base macros
+#[allow(clippy::too_many_arguments)]
+fn parse_macros<'m>(
+    macros: &'m IndexMap<String, String>,
+    overlay_macros: &'m IndexMap<String, String>,
+    source: impl Fn(&str, bool) -> RequirementSource,
+    outer_macros: Option<&HashMap<&str, RequirementExpression<'static>>>,
+    item_names: &HashMap<&str, ItemId>,
+    event_names: &HashMap<&str, EventId>,
+    trick_names: &HashMap<&str, TrickId>,
+) -> anyhow::Result<HashMap<&'m str, RequirementExpression<'static>>> {
+    let mut parsed: HashMap<&str, RequirementExpression<'static>> = HashMap::new();
+    let parse = |parsed: &HashMap<&str, RequirementExpression<'static>>,
+                 name: &str,
+                 req_string: &str,
+                 from_overlay: bool| {
+        let layers = match outer_macros {
+            Some(outer) => vec![outer, parsed],
+            None => vec![parsed],
+        };
+        RequirementExpression::parse(
+            req_string,
+            &source(name, from_overlay),
+            None,
+            &layers,
+            item_names,
+            event_names,
+            trick_names,
+        )
+    };
+    for (name, req_string) in macros {
+        let mut req = parse(&parsed, name, req_string, false)?;
+        if let Some(overlay_req_string) = overlay_macros.get(name) {
+            req = RequirementExpression::Or(vec![
+                req,
+                parse(&parsed, name, overlay_req_string, true)?,
+            ]);
+        }
+        parsed.insert(name, req);
+    }
+    for (name, req_string) in overlay_macros {
+        if !macros.contains_key(name) {
+            let req = parse(&parsed, name, req_string, true)?;
+            parsed.insert(name, req);
+        }
+    }
+    Ok(parsed)
+}
 
-    println!("{:?}", options);
+/// loads the base logic and the glitched overlay,
+/// the glitched requirements only contain the entries that differ from the base ones
+#[allow(clippy::type_complexity)]
+pub fn load_logic(
+    source: &LogicSource,
+    glitched: &LogicSource,
+) -> anyhow::Result<(
+    LogicContext,
+    HashMap<RequirementKey, RequirementExpression<'static>>,
+    HashMap<RequirementKey, RequirementExpression<'static>>,
+    Vec<OptionEntry>,
+)> {
+    let macros = source.read_macros()?;
+    let items = source.read_items()?;
+    let checks = source.read_checks()?;
+    let options = source.read_options()?;
 
     let stage_exit_re = Regex::new(r#"([^-]+) - ([^(-]+)( \(([^-]+)\))?$"#)?;
-    let mut regions: BTreeMap<String, RegionYaml> = BTreeMap::new();
-
-    read_logic_into("Ancient Cistern", &mut regions)?;
-    read_logic_into("Earth Temple", &mut regions)?;
-    read_logic_into("Eldin", &mut regions)?;
-    read_logic_into("Faron", &mut regions)?;
-    read_logic_into("Fire Sanctuary", &mut regions)?;
-    read_logic_into("Lanayru Mining Facility", &mut regions)?;
-    read_logic_into("Lanayru", &mut regions)?;
-    read_logic_into("Sandship", &mut regions)?;
-    read_logic_into("Sky Keep", &mut regions)?;
-    read_logic_into("Sky", &mut regions)?;
-    read_logic_into("Skyloft", &mut regions)?;
-    read_logic_into("Skyview", &mut regions)?;
-
-    let entrance_table = read_entrance_table()?;
+    let (regions, region_files) = source.read_regions()?;
+    let glitched_macros = glitched.read_overlay_macros()?;
+    let (glitched_regions, glitched_region_files) = glitched.read_overlay_regions()?;
+
+    let entrance_table = source.read_entrance_table()?;
 
     let item_names: HashMap<&str, ItemId> = items
         .iter()
         .map(|(id, item)| (item.name.as_str(), *id))
         .collect();
 
+    let bitless_trick_count = multichoice_choices(&options, TRICK_OPTION)?.len();
+    let tricks: Vec<Trick> = multichoice_choices(&options, TRICK_OPTION)?
+        .iter()
+        .chain(multichoice_choices(&options, GLITCHED_TRICK_OPTION)?)
+        .enumerate()
+        .map(|(id, name)| Trick {
+            id: TrickId(id as u16),
+            name: name.clone(),
+            ident: convert_to_upper_camel_case(name),
+        })
+        .collect();
+
+    let check_types = multichoice_choices(&options, BANNED_TYPES_OPTION)?;
+
+    // base logic can only use bitless tricks, glitched logic all of them
+    let glitched_trick_names: HashMap<&str, TrickId> = tricks
+        .iter()
+        .map(|trick| (trick.name.as_str(), trick.id))
+        .collect();
+    if glitched_trick_names.len() != tricks.len() {
+        bail!("tricks in {TRICK_OPTION} and {GLITCHED_TRICK_OPTION} have to be distinct");
+    }
+    let trick_names: HashMap<&str, TrickId> = tricks[..bitless_trick_count]
+        .iter()
+        .map(|trick| (trick.name.as_str(), trick.id))
+        .collect();
+
     let mut new_regions = Vec::new();
     let mut stages: Vec<Stage> = Vec::new();
     let mut areas = Vec::new();
     let mut locations = Vec::new();
 
-    let mut unparsed_requirements: Vec<(RequirementKey, &str)> = Vec::new();
+    // key, name in the logic file, requirement
+    let mut unparsed_requirements: Vec<(RequirementKey, &str, &str)> = Vec::new();
     let mut unparsed_events: HashMap<&str, Vec<(AreaId, &str)>> = HashMap::new();
     let mut area_rev_lookup: HashMap<(&str, &str), AreaId> = HashMap::new();
     let mut area_macros: HashMap<AreaId, &IndexMap<String, String>> = HashMap::new();
@@ -288,12 +499,22 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                     let location_id = LocationId(locations.len() as u16);
 
                     area_locations.push(location_id);
-                    unparsed_requirements
-                        .push((RequirementKey::Location(location_id), &requirement));
+                    unparsed_requirements.push((
+                        RequirementKey::Location(location_id),
+                        location_name,
+                        requirement,
+                    ));
 
                     let display_name = format!("{region_name} - {location_name}");
 
+                    let mut types = Vec::new();
                     let kind = if let Some(check) = checks.get(&display_name) {
+                        for check_type in check.types.split(',').map(str::trim) {
+                            if !check_types.iter().any(|t| t == check_type) {
+                                bail!("unknown type {check_type} for {display_name}");
+                            }
+                            types.push(check_type.to_string());
+                        }
                         LocationKind::Check {
                             vanilla_item: *item_names
                                 .get(check.orig_item.as_str())
@@ -315,6 +536,7 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                         name: location_name.clone(),
                         id: location_id,
                         kind,
+                        types,
                     });
                 }
 
@@ -380,13 +602,18 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                 let area_id = area_rev_lookup[&(stage_name.as_str(), area_name.as_str())];
 
                 for (exit_area, requirement) in area.logic_exits.iter() {
-                    let other_area_id = area_rev_lookup[&(stage_name.as_str(), exit_area.as_str())];
+                    let other_area_id = *area_rev_lookup
+                        .get(&(stage_name.as_str(), exit_area.as_str()))
+                        .with_context(|| {
+                            format!("invalid logic exit {exit_area} in {stage_name} - {area_name}")
+                        })?;
                     unparsed_requirements.push((
                         RequirementKey::LogicExit {
                             from: area_id,
                             to: other_area_id,
                         },
-                        &requirement,
+                        exit_area,
+                        requirement,
                     ));
                     areas[area_id.0 as usize].logic_exits.push(other_area_id);
                     areas[other_area_id.0 as usize]
@@ -399,7 +626,13 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                         let other_stagename = &captures[1];
                         let other_areaname = &captures[2];
                         let disambiguation = captures.get(4).map(|c| c.as_str());
-                        let other_area_id = area_rev_lookup[&(other_stagename, other_areaname)];
+                        let other_area_id = *area_rev_lookup
+                            .get(&(other_stagename, other_areaname))
+                            .with_context(|| {
+                                format!(
+                                    "invalid stage exit {exit_name} in {stage_name} - {area_name}"
+                                )
+                            })?;
 
                         let mut create_connection = |connection_def: Option<&ConnectionYaml>| {
                             let double_door = connection_def
@@ -419,11 +652,17 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
 
                             let entrance_id = EntranceId(entrances.len() as u16);
 
-                            let connection_shuffle_type = if connection_def.is_some() {
-                                ConnectionShuffleType::Other
-                            } else {
-                                ConnectionShuffleType::Never
-                            };
+                            let connection_shuffle_type =
+                                match connection_def.map(|c| c.connection_type) {
+                                    None => ConnectionShuffleType::Never,
+                                    Some(Some(ConnectionTypeYaml::TrialGate)) => {
+                                        ConnectionShuffleType::TrialGate
+                                    }
+                                    Some(Some(ConnectionTypeYaml::TrialExit)) => {
+                                        ConnectionShuffleType::TrialExit
+                                    }
+                                    Some(_) => ConnectionShuffleType::Other,
+                                };
 
                             entrances.push(Entrance {
                                 id: entrance_id,
@@ -450,18 +689,23 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                                 },
                                 door_connection: DoorConnection::No, // maybe filled later
                                 connection_shuffle_type,
-                                patch_info: connection_def.map(|info| EntrancePatchInfo {
-                                    entrance_id: info.orig.entrance,
-                                    layer: info.orig.layer,
-                                    room: info.orig.room,
-                                    stage_name: info.orig.stage.clone(),
-                                }),
+                                patch_info: connection_def.and_then(|info| info.orig.as_ref()).map(
+                                    |orig| EntrancePatchInfo {
+                                        entrance_id: orig.entrance,
+                                        layer: orig.layer,
+                                        room: orig.room,
+                                        stage_name: orig.stage.clone(),
+                                    },
+                                ),
                             });
 
                             let exit_id = ExitId(exits.len() as u16);
 
-                            unparsed_requirements
-                                .push((RequirementKey::Exit(exit_id), &requirement));
+                            unparsed_requirements.push((
+                                RequirementKey::Exit(exit_id),
+                                exit_name,
+                                requirement,
+                            ));
 
                             exits.push(Exit {
                                 id: exit_id,
@@ -573,7 +817,7 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
                             create_connection(None);
                         }
                     } else {
-                        panic!("invalid stage exit: {exit_name}");
+                        bail!("invalid stage exit {exit_name} in {stage_name} - {area_name}");
                     }
                 }
             }
@@ -603,7 +847,9 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
     let mut sorted_events: Vec<_> = unparsed_events.into_iter().collect();
     sorted_events.sort_unstable_by_key(|(key, _)| *key);
 
-    let events: Vec<Event> = sorted_events.iter().map(|(key, _)| key)
+    let events: Vec<Event> = sorted_events
+        .iter()
+        .map(|(key, _)| key)
         .enumerate()
         .map(|(id, event)| Event {
             id: EventId(id as u16),
@@ -617,87 +863,241 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
         .map(|event| (event.name.as_str(), event.id))
         .collect();
 
-    // parse requirements
-    // parse macros first
-
-    let mut parsed_macros: HashMap<&str, RequirementExpression<'static>> = HashMap::new();
-    for (name, req_string) in &macros {
-        let req = RequirementExpression::parse(
-            &req_string,
-            None,
-            &[&parsed_macros],
-            &item_names,
-            &event_names,
-        )?;
-        parsed_macros.insert(&name, req);
-    }
-
-    // parse local area macros
-    let parsed_area_macros: HashMap<AreaId, HashMap<&str, RequirementExpression<'static>>> =
-        area_macros
-            .iter()
-            .map(|(area, macros)| {
-                let mut for_area = HashMap::new();
-                for (name, req_string) in macros.iter() {
-                    let req = RequirementExpression::parse(
-                        &req_string,
-                        None,
-                        &[&parsed_macros, &for_area],
-                        &item_names,
-                        &event_names,
-                    )
-                    .unwrap();
-                    for_area.insert(&name, req);
-                }
-                (*area, for_area)
-            })
-            .collect();
-
-    let mut requirements: HashMap<RequirementKey, RequirementExpression<'static>> = HashMap::new();
-
-    let empty_macros = HashMap::new();
-    for (req_key, req_string) in unparsed_requirements.iter() {
+    // collect the additions from the glitched logic, they can only refer to existing areas,
+    // locations, events and exits, but can add new logic exits
+    let mut named_requirements: HashMap<(AreaId, &str), Vec<RequirementKey>> = HashMap::new();
+    for (req_key, name, _) in unparsed_requirements.iter() {
         let area_id = match req_key {
             RequirementKey::Exit(exit_id) => exits[exit_id.0 as usize].area,
             RequirementKey::LogicExit { from, .. } => *from,
             RequirementKey::Location(location_id) => locations[location_id.0 as usize].area,
             RequirementKey::Event(_) => unreachable!(),
         };
+        named_requirements
+            .entry((area_id, name))
+            .or_default()
+            .push(*req_key);
+    }
+
+    let mut glitched_overlay = LogicOverlay {
+        macros: glitched_macros,
+        ..Default::default()
+    };
+    let mut new_logic_exits = Vec::new();
+    for (region_name, region) in glitched_regions.iter() {
+        let file = &glitched_region_files[region_name];
+        for (stage_name, stage) in region.stages.iter() {
+            for (area_name, area) in stage.areas.iter() {
+                let area_id = *area_rev_lookup
+                    .get(&(stage_name.as_str(), area_name.as_str()))
+                    .with_context(|| format!("{file}: unknown area {stage_name} - {area_name}"))?;
 
-        let req = RequirementExpression::parse(
-            &req_string,
-            Some(area_id),
-            &[
-                &parsed_macros,
-                parsed_area_macros.get(&area_id).unwrap_or(&empty_macros),
-            ],
+                if !area.macros.is_empty() {
+                    glitched_overlay.area_macros.insert(area_id, &area.macros);
+                }
+
+                for (event_name, requirement) in area.events.iter() {
+                    let event_id = *event_names
+                        .get(event_name.as_str())
+                        .with_context(|| format!("{file}: unknown event {event_name}"))?;
+                    glitched_overlay
+                        .requirements
+                        .entry(RequirementKey::Event(event_id))
+                        .or_default()
+                        .push((area_id, event_name, requirement));
+                }
+
+                for (name, requirement) in area
+                    .locations
+                    .iter()
+                    .chain(area.map_exits.iter())
+                    .chain(area.logic_exits.iter())
+                {
+                    let req_keys =
+                        if let Some(req_keys) = named_requirements.get(&(area_id, name.as_str())) {
+                            req_keys.clone()
+                        } else if let Some(other_area_id) = area
+                            .logic_exits
+                            .contains_key(name)
+                            .then(|| area_rev_lookup.get(&(stage_name.as_str(), name.as_str())))
+                            .flatten()
+                        {
+                            new_logic_exits.push((area_id, *other_area_id, name.as_str()));
+                            vec![RequirementKey::LogicExit {
+                                from: area_id,
+                                to: *other_area_id,
+                            }]
+                        } else {
+                            bail!(
+                            "{file}: unknown location or exit {name} in {stage_name} - {area_name}"
+                        );
+                        };
+                    for req_key in req_keys {
+                        glitched_overlay
+                            .requirements
+                            .entry(req_key)
+                            .or_default()
+                            .push((area_id, name, requirement));
+                    }
+                }
+            }
+        }
+    }
+
+    // logic exits that only exist in glitched logic are impossible in the base logic
+    for (area_id, other_area_id, name) in new_logic_exits {
+        unparsed_requirements.push((
+            RequirementKey::LogicExit {
+                from: area_id,
+                to: other_area_id,
+            },
+            name,
+            "Impossible",
+        ));
+        areas[area_id.0 as usize].logic_exits.push(other_area_id);
+        areas[other_area_id.0 as usize]
+            .logic_entrances
+            .push(area_id);
+    }
+
+    // parse requirements
+    // parse macros first
+
+    let requirement_source = |files: &HashMap<String, String>, area_id: AreaId, name: &str| {
+        let area = &areas[area_id.0 as usize];
+        let region_name = &new_regions[area.region.0 as usize].name;
+        RequirementSource {
+            // the overlay doesn't need to have a file for every region
+            file: files.get(region_name).cloned().unwrap_or_default(),
+            path: format!(
+                "{region_name}/{}/{}/{name}",
+                stages[area.stage.0 as usize].name, area.name
+            ),
+        }
+    };
+
+    // parses all requirements, with the additions from the overlay OR-ed on
+    let parse_requirements = |overlay: &LogicOverlay, trick_names: &HashMap<&str, TrickId>| {
+        let macro_source = |name: &str, from_overlay: bool| RequirementSource {
+            file: if from_overlay {
+                glitched.display_path(&glitched.macro_file())
+            } else {
+                source.display_path(&source.macro_file())
+            },
+            path: name.to_string(),
+        };
+        let parsed_macros = parse_macros(
+            &macros,
+            &overlay.macros,
+            macro_source,
+            None,
             &item_names,
             &event_names,
+            trick_names,
         )?;
-        requirements.insert(*req_key, req);
-    }
-
-    for (event, raw_reqs) in sorted_events.iter() {
-        let event_id = event_names[event];
-        let mut reqs = Vec::new();
-        for (area_id, req_string) in raw_reqs {
-            let req = RequirementExpression::parse(
-                &req_string,
-                Some(*area_id),
-                &[
-                    &parsed_macros,
-                    parsed_area_macros.get(area_id).unwrap_or(&empty_macros),
-                ],
+
+        // parse local area macros
+        let empty_macros = IndexMap::new();
+        let mut parsed_area_macros: HashMap<AreaId, HashMap<&str, RequirementExpression<'static>>> =
+            HashMap::new();
+        for area in area_macros.keys().chain(overlay.area_macros.keys()) {
+            if parsed_area_macros.contains_key(area) {
+                continue;
+            }
+            let for_area = parse_macros(
+                area_macros.get(area).copied().unwrap_or(&empty_macros),
+                overlay
+                    .area_macros
+                    .get(area)
+                    .copied()
+                    .unwrap_or(&empty_macros),
+                |name, from_overlay| {
+                    if from_overlay {
+                        requirement_source(&glitched_region_files, *area, name)
+                    } else {
+                        requirement_source(&region_files, *area, name)
+                    }
+                },
+                Some(&parsed_macros),
                 &item_names,
                 &event_names,
+                trick_names,
             )?;
-            reqs.push(req);
+            parsed_area_macros.insert(*area, for_area);
         }
-        requirements.insert(
-            RequirementKey::Event(event_id),
-            RequirementExpression::Or(reqs),
-        );
-    }
+
+        let empty_parsed_macros = HashMap::new();
+        let parse_in_area =
+            |files: &HashMap<String, String>, area_id: AreaId, name: &str, req_string: &str| {
+                RequirementExpression::parse(
+                    req_string,
+                    &requirement_source(files, area_id, name),
+                    Some(area_id),
+                    &[
+                        &parsed_macros,
+                        parsed_area_macros
+                            .get(&area_id)
+                            .unwrap_or(&empty_parsed_macros),
+                    ],
+                    &item_names,
+                    &event_names,
+                    trick_names,
+                )
+            };
+        let overlay_requirements = |req_key: &RequirementKey| -> anyhow::Result<Vec<_>> {
+            overlay
+                .requirements
+                .get(req_key)
+                .into_iter()
+                .flatten()
+                .map(|(area_id, name, req_string)| {
+                    parse_in_area(&glitched_region_files, *area_id, name, req_string)
+                })
+                .collect()
+        };
+
+        let mut requirements: HashMap<RequirementKey, RequirementExpression<'static>> =
+            HashMap::new();
+
+        for (req_key, name, req_string) in unparsed_requirements.iter() {
+            let area_id = match req_key {
+                RequirementKey::Exit(exit_id) => exits[exit_id.0 as usize].area,
+                RequirementKey::LogicExit { from, .. } => *from,
+                RequirementKey::Location(location_id) => locations[location_id.0 as usize].area,
+                RequirementKey::Event(_) => unreachable!(),
+            };
+
+            let req = parse_in_area(&region_files, area_id, name, req_string)?;
+            let additions = overlay_requirements(req_key)?;
+            if additions.is_empty() {
+                requirements.insert(*req_key, req);
+            } else {
+                let mut reqs = vec![req];
+                reqs.extend(additions);
+                requirements.insert(*req_key, RequirementExpression::Or(reqs));
+            }
+        }
+
+        for (event, raw_reqs) in sorted_events.iter() {
+            let event_id = event_names[event];
+            let mut reqs = Vec::new();
+            for (area_id, req_string) in raw_reqs {
+                reqs.push(parse_in_area(&region_files, *area_id, event, req_string)?);
+            }
+            reqs.extend(overlay_requirements(&RequirementKey::Event(event_id))?);
+            requirements.insert(
+                RequirementKey::Event(event_id),
+                RequirementExpression::Or(reqs),
+            );
+        }
+        anyhow::Ok(requirements)
+    };
+
+    let requirements = parse_requirements(&LogicOverlay::default(), &trick_names)?;
+    let glitched_requirements = parse_requirements(&glitched_overlay, &glitched_trick_names)?
+        .into_iter()
+        .filter(|(req_key, req)| requirements.get(req_key) != Some(req))
+        .collect();
 
     let ctx = LogicContext {
         areas,
@@ -708,6 +1108,7 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
         locations,
         regions: new_regions,
         stages,
+        tricks,
     };
 
     for i in &ctx.areas {
@@ -742,5 +1143,9 @@ pub fn load_logic() -> anyhow::Result<(LogicContext, HashMap<RequirementKey, Req
         assert!(i.id == i.id.ctx(&ctx).id);
     }
 
-    Ok((ctx, requirements, options))
+    for i in &ctx.tricks {
+        assert!(i.id == i.id.ctx(&ctx).id);
+    }
+
+    Ok((ctx, requirements, glitched_requirements, options))
 }
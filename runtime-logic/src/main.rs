@@ -1,18 +1,49 @@
-use std::{fs::File, io::Write};
+use std::{fs::File, io::Write, path::PathBuf, process::ExitCode};
 
 use dumper::dump;
-use loader::load_logic;
+use lint::lint;
+use loader::{load_logic, LogicSource};
 
 // mod bitset;
 mod dumper;
+mod lint;
 mod loader;
 mod requirements;
 mod structure;
 
-fn main() {
-    let (ctx, base_requirements, options) = load_logic().unwrap();
-    let dumped = dump(&ctx, &base_requirements, &options).unwrap();
+/// the directory containing the logic yaml files, if not given on the command line
+const DEFAULT_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+/// where the generated code goes, independent of the logic root
+const OUTPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../generated/src/generated.rs");
+
+/// usage: runtime-logic [lint] [root]
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    let is_lint = args.next_if(|arg| arg == "lint").is_some();
+    let root = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_ROOT));
+    let source = LogicSource::new(&root, "bitless");
+    let glitched = source.overlay("glitched");
+
+    if is_lint {
+        let findings = lint(&source, &glitched).unwrap();
+        for finding in &findings {
+            println!("{finding}");
+        }
+        return if findings.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    let (ctx, base_requirements, glitched_requirements, options) =
+        load_logic(&source, &glitched).unwrap();
+    let dumped = dump(&ctx, &base_requirements, &glitched_requirements, &options).unwrap();
     // println!("{}", &dumped);
-    let mut outf = File::create("../generated/src/generated.rs").unwrap();
+    let mut outf = File::create(OUTPUT).unwrap();
     outf.write_all(dumped.as_bytes()).unwrap();
+    ExitCode::SUCCESS
 }
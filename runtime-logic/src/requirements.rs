@@ -1,7 +1,7 @@
 use core::fmt;
 use std::{collections::HashMap, fmt::Debug};
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
 use heck::{ToPascalCase, ToSnekCase};
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
@@ -10,12 +10,10 @@ use syn::Ident;
 
 use crate::{
     dumper::convert_to_upper_camel_case,
-    structure::{
-        AreaId, ContextLoadable, EventId, ItemId, LogicContext, TimeOfDay,
-    },
+    structure::{AreaId, ContextLoadable, EventId, ItemId, LogicContext, TimeOfDay, TrickId},
 };
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum RequirementExpression<'a> {
     And(Vec<RequirementExpression<'a>>),
     Or(Vec<RequirementExpression<'a>>),
@@ -25,6 +23,10 @@ pub enum RequirementExpression<'a> {
     // it otherwise uses the specific allowed TimeOfDay
     Area(AreaId, TimeOfDay),
     Fixed(bool),
+    Trick(TrickId),
+    // the logic assumes that having more items never makes anything unreachable,
+    // so the parser only allows this on options and tricks
+    Not(Box<RequirementExpression<'a>>),
     OptionEnabled {
         option: String,
         enabled: bool,
@@ -34,40 +36,100 @@ pub enum RequirementExpression<'a> {
         value: String,
         not: bool,
     },
+    OptionContains {
+        option: String,
+        value: String,
+        not: bool,
+    },
+    OptionCompare {
+        option: String,
+        comparison: Comparison,
+        value: usize,
+    },
     Ref(&'a RequirementExpression<'a>),
 }
 
+/// where to continue after an instruction of a compiled requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Next {
+    Jump(usize),
+    Done(bool),
+}
+
+struct Instruction<'e, 'a> {
+    // a base requirement, None always passes
+    test: Option<&'e RequirementExpression<'a>>,
+    if_true: Next,
+    if_false: Next,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    AtLeast,
+    AtMost,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            ">=" => Some(Comparison::AtLeast),
+            "<=" => Some(Comparison::AtMost),
+            _ => None,
+        }
+    }
+}
+
 impl<'a> RequirementExpression<'a> {
     pub fn dump(&self, ctx: &LogicContext) -> TokenStream {
         match self {
             RequirementExpression::And(exprs) => {
                 let dumped_exprs = exprs.iter().map(|e| e.dump(ctx));
-                // TODO: make this a Cow?
-                quote!(RequirementExpression::And(vec![#(#dumped_exprs),*]))
+                quote!(RequirementExpression::And(ExpressionList::Borrowed(&[#(#dumped_exprs),*])))
             }
             RequirementExpression::Or(exprs) => {
                 let dumped_exprs = exprs.iter().map(|e| e.dump(ctx));
-                quote!(RequirementExpression::Or(vec![#(#dumped_exprs),*]))
+                quote!(RequirementExpression::Or(ExpressionList::Borrowed(&[#(#dumped_exprs),*])))
+            }
+            RequirementExpression::Not(expr) => {
+                let dumped_expr = expr.dump(ctx);
+                quote!(RequirementExpression::Not(&#dumped_expr))
             }
+            RequirementExpression::Ref(rf) => rf.dump(ctx),
+            _ => self.dump_base(ctx, &quote!(RequirementExpression)),
+        }
+    }
+
+    /// dumps a base requirement as a variant of `ty`, which can be the
+    /// `RequirementExpression` or the `Test` of an instruction
+    fn dump_base(&self, ctx: &LogicContext, ty: &TokenStream) -> TokenStream {
+        match self {
             RequirementExpression::Item(item, count) => {
                 let item_ident = Ident::new(&item.ctx(ctx).ident, Span::call_site());
-                quote!(RequirementExpression::Item(Item::#item_ident, #count))
+                quote!(#ty::Item(Item::#item_ident, #count))
             }
             RequirementExpression::Area(area, tod) => {
                 let area_ident = Ident::new(&area.ctx(ctx).ident, Span::call_site());
                 let tod_tokens = tod.to_token();
-                quote!(RequirementExpression::Area(Area::#area_ident, #tod_tokens))
+                quote!(#ty::Area(Area::#area_ident, #tod_tokens))
             }
             RequirementExpression::Fixed(value) => {
-                quote!(RequirementExpression::Fixed(#value))
+                quote!(#ty::Fixed(#value))
             }
             RequirementExpression::Event(event) => {
                 let event_ident = Ident::new(&event.ctx(ctx).ident, Span::call_site());
-                quote!(RequirementExpression::Event(Event::#event_ident))
+                quote!(#ty::Event(Event::#event_ident))
+            }
+            RequirementExpression::Trick(trick) => {
+                let trick_ident = Ident::new(&trick.ctx(ctx).ident, Span::call_site());
+                quote!(#ty::Trick(Trick::#trick_ident))
             }
             RequirementExpression::OptionEnabled { option, enabled } => {
                 let opt_ident = Ident::new(&option.to_snek_case(), Span::call_site());
-                quote!(RequirementExpression::Option(|options| options.#opt_ident == #enabled))
+                if *enabled {
+                    quote!(#ty::Option(|options| options.#opt_ident))
+                } else {
+                    quote!(#ty::Option(|options| !options.#opt_ident))
+                }
             }
             RequirementExpression::OptionIs { option, value, not } => {
                 let opt_ident = Ident::new(&option.to_snek_case(), Span::call_site());
@@ -75,15 +137,163 @@ impl<'a> RequirementExpression<'a> {
                 let value_ident =
                     Ident::new(&convert_to_upper_camel_case(value), Span::call_site());
                 if *not {
-                    quote!(RequirementExpression::Option(|options| options.#opt_ident != #opt_enum_ident::#value_ident))
+                    quote!(#ty::Option(|options| options.#opt_ident != #opt_enum_ident::#value_ident))
                 } else {
-                    quote!(RequirementExpression::Option(|options| options.#opt_ident == #opt_enum_ident::#value_ident))
+                    quote!(#ty::Option(|options| options.#opt_ident == #opt_enum_ident::#value_ident))
                 }
             }
-            RequirementExpression::Ref(rf) => rf.dump(ctx),
+            RequirementExpression::OptionContains { option, value, not } => {
+                let opt_ident = Ident::new(&option.to_snek_case(), Span::call_site());
+                let opt_enum_ident = Ident::new(&option.to_pascal_case(), Span::call_site());
+                let value_ident =
+                    Ident::new(&convert_to_upper_camel_case(value), Span::call_site());
+                if *not {
+                    quote!(#ty::Option(|options| !options.#opt_ident.has(#opt_enum_ident::#value_ident)))
+                } else {
+                    quote!(#ty::Option(|options| options.#opt_ident.has(#opt_enum_ident::#value_ident)))
+                }
+            }
+            RequirementExpression::OptionCompare {
+                option,
+                comparison,
+                value,
+            } => {
+                let opt_ident = Ident::new(&option.to_snek_case(), Span::call_site());
+                match comparison {
+                    Comparison::AtLeast => {
+                        quote!(#ty::Option(|options| options.#opt_ident >= #value))
+                    }
+                    Comparison::AtMost => {
+                        quote!(#ty::Option(|options| options.#opt_ident <= #value))
+                    }
+                }
+            }
+            RequirementExpression::And(_)
+            | RequirementExpression::Or(_)
+            | RequirementExpression::Not(_)
+            | RequirementExpression::Ref(_) => unreachable!("not a base requirement"),
         }
     }
 
+    /// compiles this requirement into instructions that each test one base requirement and then
+    /// either jump forward or finish, `&` and `|` short circuit
+    fn compile(&self) -> Vec<Instruction<'_, 'a>> {
+        // instructions are generated back to front, so jump targets are always known,
+        // jumps count from the end until the order is reversed
+        let mut reversed = Vec::new();
+        let mut entry = self.compile_into(Next::Done(true), Next::Done(false), &mut reversed);
+        if entry != Next::Jump(reversed.len().wrapping_sub(1)) {
+            // the first instruction has to be the entry
+            reversed.push(Instruction {
+                test: None,
+                if_true: entry,
+                if_false: entry,
+            });
+            entry = Next::Jump(reversed.len() - 1);
+        }
+        debug_assert_eq!(entry, Next::Jump(reversed.len() - 1));
+        let last = reversed.len() - 1;
+        let fix_jump = |next: Next| match next {
+            Next::Jump(from_end) => Next::Jump(last - from_end),
+            done => done,
+        };
+        reversed
+            .into_iter()
+            .rev()
+            .map(|instruction| Instruction {
+                test: instruction.test,
+                if_true: fix_jump(instruction.if_true),
+                if_false: fix_jump(instruction.if_false),
+            })
+            .collect()
+    }
+
+    /// returns where to start evaluating this requirement
+    fn compile_into<'e>(
+        &'e self,
+        if_true: Next,
+        if_false: Next,
+        reversed: &mut Vec<Instruction<'e, 'a>>,
+    ) -> Next {
+        match self {
+            RequirementExpression::And(exprs) => {
+                let mut entry = if_true;
+                for expr in exprs.iter().rev() {
+                    entry = expr.compile_into(entry, if_false, reversed);
+                }
+                entry
+            }
+            RequirementExpression::Or(exprs) => {
+                let mut entry = if_false;
+                for expr in exprs.iter().rev() {
+                    entry = expr.compile_into(if_true, entry, reversed);
+                }
+                entry
+            }
+            RequirementExpression::Not(expr) => expr.compile_into(if_false, if_true, reversed),
+            RequirementExpression::Fixed(true) => if_true,
+            RequirementExpression::Fixed(false) => if_false,
+            RequirementExpression::Ref(rf) => rf.compile_into(if_true, if_false, reversed),
+            _ => {
+                reversed.push(Instruction {
+                    test: Some(self),
+                    if_true,
+                    if_false,
+                });
+                Next::Jump(reversed.len() - 1)
+            }
+        }
+    }
+
+    /// if the requirement uses anything the fill collects
+    fn depends_on_progress(&self) -> bool {
+        match self {
+            RequirementExpression::And(exprs) | RequirementExpression::Or(exprs) => {
+                exprs.iter().any(|expr| expr.depends_on_progress())
+            }
+            RequirementExpression::Item(..)
+            | RequirementExpression::Event(_)
+            | RequirementExpression::Area(..) => true,
+            RequirementExpression::Not(expr) => expr.depends_on_progress(),
+            RequirementExpression::Ref(rf) => rf.depends_on_progress(),
+            RequirementExpression::Fixed(_)
+            | RequirementExpression::Trick(_)
+            | RequirementExpression::OptionEnabled { .. }
+            | RequirementExpression::OptionIs { .. }
+            | RequirementExpression::OptionContains { .. }
+            | RequirementExpression::OptionCompare { .. } => false,
+        }
+    }
+
+    /// fails if the expression is too large for the jump targets
+    pub fn dump_bytecode(&self, ctx: &LogicContext) -> anyhow::Result<TokenStream> {
+        let dump_next = |next: Next| -> anyhow::Result<TokenStream> {
+            Ok(match next {
+                Next::Jump(target) => {
+                    let Ok(target) = u16::try_from(target) else {
+                        bail!("jump target {target} doesn't fit into u16");
+                    };
+                    quote!(Next::Jump(#target))
+                }
+                Next::Done(result) => quote!(Next::Done(#result)),
+            })
+        };
+        let instructions = self
+            .compile()
+            .into_iter()
+            .map(|instruction| {
+                let test = match instruction.test {
+                    Some(test) => test.dump_base(ctx, &quote!(Test)),
+                    None => quote!(Test::Fixed(true)),
+                };
+                let if_true = dump_next(instruction.if_true)?;
+                let if_false = dump_next(instruction.if_false)?;
+                Ok(quote!(Instruction { test: #test, if_true: #if_true, if_false: #if_false }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(quote!(&[#(#instructions),*]))
+    }
+
     fn display_helper(&self, ctx: &LogicContext, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             RequirementExpression::And(exprs) => {
@@ -113,6 +323,13 @@ impl<'a> RequirementExpression<'a> {
                 ))?;
             }
             RequirementExpression::Fixed(val) => f.write_fmt(format_args!("{}", val))?,
+            RequirementExpression::Trick(trick) => {
+                f.write_fmt(format_args!("Trick: {}", trick.name(ctx)))?;
+            }
+            RequirementExpression::Not(expr) => {
+                f.write_str("NOT ")?;
+                expr.display_helper(ctx, f)?;
+            }
             RequirementExpression::Ref(req) => req.display_helper(ctx, f)?,
             _ => {} // RequirementExpression::Option(fun) => fun(options),
         }
@@ -137,6 +354,8 @@ impl<'a> RequirementExpression<'a> {
             RequirementExpression::Event(event_id) => RequirementExpression::Event(*event_id),
             RequirementExpression::Area(area, tod) => RequirementExpression::Area(*area, *tod),
             RequirementExpression::Fixed(value) => RequirementExpression::Fixed(*value),
+            RequirementExpression::Trick(trick) => RequirementExpression::Trick(*trick),
+            RequirementExpression::Not(expr) => RequirementExpression::Not(Box::new(expr.owned())),
             RequirementExpression::OptionEnabled { option, enabled } => {
                 RequirementExpression::OptionEnabled {
                     option: option.clone(),
@@ -150,6 +369,22 @@ impl<'a> RequirementExpression<'a> {
                     not: *not,
                 }
             }
+            RequirementExpression::OptionContains { option, value, not } => {
+                RequirementExpression::OptionContains {
+                    option: option.clone(),
+                    value: value.clone(),
+                    not: *not,
+                }
+            }
+            RequirementExpression::OptionCompare {
+                option,
+                comparison,
+                value,
+            } => RequirementExpression::OptionCompare {
+                option: option.clone(),
+                comparison: *comparison,
+                value: *value,
+            },
             RequirementExpression::Ref(inner) => inner.owned(),
         }
     }
@@ -166,40 +401,270 @@ impl<'a> Debug for ReqDisplay<'a> {
     }
 }
 
-#[derive(PartialEq)]
-enum LogicType {
-    None,
+/// where a requirement string comes from, used for error messages
+#[derive(Debug, Clone)]
+pub struct RequirementSource {
+    pub file: String,
+    /// region/stage/area/location (or whatever identifies the requirement in the file)
+    pub path: String,
+}
+
+impl fmt::Display for RequirementSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.path)
+    }
+}
+
+/// the base requirements of a requirement string, without operators and parentheses
+pub fn base_requirements(s: &str) -> anyhow::Result<Vec<&str>> {
+    Ok(tokenize(s)?
+        .into_iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Atom(atom) => Some(atom),
+            _ => None,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind<'s> {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Atom(&'s str),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'s> {
+    kind: TokenKind<'s>,
+    // 1 based, in chars
+    column: usize,
+}
+
+/// splits a requirement string into operators, parentheses and base requirements,
+/// operators inside of quotes are part of the base requirement
+/// `not` is only an operator at the start of a base requirement
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    // byte offset and column of the atom that is currently read
+    let mut atom_start: Option<(usize, usize)> = None;
+    let mut quote_start: Option<usize> = None;
+    // chars of a keyword that still need to be skipped
+    let mut skip = 0;
+
+    fn finish_atom<'s>(
+        s: &'s str,
+        tokens: &mut Vec<Token<'s>>,
+        atom_start: &mut Option<(usize, usize)>,
+        end: usize,
+    ) {
+        if let Some((start, column)) = atom_start.take() {
+            tokens.push(Token {
+                kind: TokenKind::Atom(s[start..end].trim_end()),
+                column,
+            });
+        }
+    }
+
+    for (column, (offset, c)) in s.char_indices().enumerate() {
+        let column = column + 1;
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        if quote_start.is_some() {
+            if c == '"' {
+                quote_start = None;
+            }
+            continue;
+        }
+        let kind = match c {
+            '&' => TokenKind::And,
+            '|' => TokenKind::Or,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            c if c.is_whitespace() => continue,
+            'n' if atom_start.is_none()
+                && s[offset..].starts_with("not")
+                && s[offset + 3..]
+                    .chars()
+                    .next()
+                    .is_some_and(|next| next.is_whitespace() || next == '(') =>
+            {
+                skip = 2;
+                TokenKind::Not
+            }
+            c => {
+                if c == '"' {
+                    quote_start = Some(column);
+                }
+                if atom_start.is_none() {
+                    atom_start = Some((offset, column));
+                }
+                continue;
+            }
+        };
+        finish_atom(s, &mut tokens, &mut atom_start, offset);
+        tokens.push(Token { kind, column });
+    }
+    if let Some(column) = quote_start {
+        bail!("{column}: unterminated quote");
+    }
+    finish_atom(s, &mut tokens, &mut atom_start, s.len());
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
     Or,
     And,
 }
 
-fn find_closing_parenthesis(s: &str, start_index: usize) -> Option<usize> {
-    // assert that we start with the parenthesis already open
-    let mut par_depth = 1;
-    for (idx, c) in s.chars().enumerate().skip(start_index) {
-        match c {
-            '(' => par_depth += 1,
-            ')' => {
-                par_depth -= 1;
-                if par_depth == 0 {
-                    return Some(idx);
+impl Operator {
+    // higher binds tighter
+    fn binding_power(&self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+        }
+    }
+}
+
+/// precedence climbing parser over the tokens of one requirement string,
+/// turning base requirements into expressions is left to `parse_atom`
+struct Parser<'s, F> {
+    tokens: Vec<Token<'s>>,
+    pos: usize,
+    // column after the end of the string, to report unexpected ends
+    end_column: usize,
+    parse_atom: F,
+}
+
+impl<'s, F> Parser<'s, F>
+where
+    F: FnMut(&'s str) -> anyhow::Result<RequirementExpression<'static>>,
+{
+    fn peek(&self) -> Option<Token<'s>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next_column(&self) -> usize {
+        self.peek().map_or(self.end_column, |t| t.column)
+    }
+
+    fn parse(mut self) -> anyhow::Result<RequirementExpression<'static>> {
+        let expr = self.parse_expr(0)?;
+        if let Some(token) = self.peek() {
+            // only a closing parenthesis can stop the top level expression early
+            bail!("{}: unmatched closing parenthesis", token.column);
+        }
+        Ok(expr)
+    }
+
+    fn parse_expr(
+        &mut self,
+        min_binding_power: u8,
+    ) -> anyhow::Result<RequirementExpression<'static>> {
+        let mut lhs = self.parse_primary()?;
+        loop {
+            let operator = match self.peek() {
+                None => break,
+                Some(Token {
+                    kind: TokenKind::Close,
+                    ..
+                }) => break,
+                Some(Token {
+                    kind: TokenKind::And,
+                    ..
+                }) => Operator::And,
+                Some(Token {
+                    kind: TokenKind::Or,
+                    ..
+                }) => Operator::Or,
+                Some(token) => bail!("{}: expected & or |", token.column),
+            };
+            if operator.binding_power() < min_binding_power {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_expr(operator.binding_power() + 1)?;
+            lhs = match (operator, lhs) {
+                (Operator::And, RequirementExpression::And(mut exprs)) => {
+                    exprs.push(rhs);
+                    RequirementExpression::And(exprs)
                 }
+                (Operator::Or, RequirementExpression::Or(mut exprs)) => {
+                    exprs.push(rhs);
+                    RequirementExpression::Or(exprs)
+                }
+                (Operator::And, lhs) => RequirementExpression::And(vec![lhs, rhs]),
+                (Operator::Or, lhs) => RequirementExpression::Or(vec![lhs, rhs]),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<RequirementExpression<'static>> {
+        let Some(token) = self.peek() else {
+            bail!("{}: unexpected end of requirement", self.end_column);
+        };
+        self.pos += 1;
+        match token.kind {
+            TokenKind::Atom(atom) => {
+                (self.parse_atom)(atom).map_err(|e| anyhow!("{}: {e}", token.column))
+            }
+            TokenKind::Open => {
+                let inner = self.parse_expr(0)?;
+                match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => bail!(
+                        "{}: closing parenthesis for column {} not found",
+                        self.next_column(),
+                        token.column
+                    ),
+                }
+            }
+            TokenKind::Not => {
+                let inner = self.parse_primary()?;
+                if inner.depends_on_progress() {
+                    bail!(
+                        "{}: not can only be used on options and tricks, \
+                        more items, events or areas must never fail a requirement",
+                        token.column
+                    );
+                }
+                Ok(RequirementExpression::Not(Box::new(inner)))
+            }
+            TokenKind::Close => bail!("{}: unexpected closing parenthesis", token.column),
+            TokenKind::And | TokenKind::Or => {
+                bail!("{}: expected requirement before operator", token.column)
             }
-            _ => {}
         }
     }
-    None
 }
 
 impl RequirementExpression<'static> {
+    #[allow(clippy::too_many_arguments)]
     pub fn parse(
         string: &str,
+        source: &RequirementSource,
         area: Option<AreaId>,
         macros: &[&HashMap<&str, RequirementExpression<'static>>],
         item_names: &HashMap<&str, ItemId>,
         event_names: &HashMap<&str, EventId>,
+        trick_names: &HashMap<&str, TrickId>,
     ) -> anyhow::Result<Self> {
-        let base = Self::parse_rec(string, area, macros, item_names, event_names)?;
+        let base =
+            Self::parse_expression(string, area, macros, item_names, event_names, trick_names)
+                .map_err(|e| anyhow!("{source}:{e}"))?;
         if let Some(area) = area {
             let area_req = RequirementExpression::Area(area, TimeOfDay::all());
             Ok(match base {
@@ -213,91 +678,33 @@ impl RequirementExpression<'static> {
             Ok(base)
         }
     }
-    pub fn parse_rec(
+
+    /// parses the whole string, `&` binds tighter than `|`
+    /// errors start with the column they occured in
+    fn parse_expression(
         string: &str,
         area: Option<AreaId>,
         macros: &[&HashMap<&str, RequirementExpression<'static>>],
         item_names: &HashMap<&str, ItemId>,
         event_names: &HashMap<&str, EventId>,
+        trick_names: &HashMap<&str, TrickId>,
     ) -> anyhow::Result<Self> {
-        let mut logic_type = LogicType::None;
-        let mut current_rest = string.trim();
-        let mut current_level = Vec::new();
-        let split_chars: &[_] = &['&', '|'];
-        // until exhausted
-        while current_rest != "" {
-            if current_rest.starts_with('(') {
-                // handle nested logic expression
-                let closing_p = find_closing_parenthesis(current_rest, 1).with_context(|| {
-                    format!("Closing parenthesis not found on string '{}'", current_rest)
-                })?;
-                current_level.push(RequirementExpression::parse_rec(
-                    &current_rest[1..closing_p],
+        let parser = Parser {
+            tokens: tokenize(string)?,
+            pos: 0,
+            end_column: string.chars().count() + 1,
+            parse_atom: |atom| {
+                Self::parse_base_requirement(
+                    atom,
                     area,
                     macros,
                     item_names,
                     event_names,
-                )?);
-                current_rest = current_rest[closing_p + 1..].trim();
-                continue;
-            }
-            // find either '&' or '|', only the same type can be on one level
-            if let Some(pos) = current_rest.find(split_chars) {
-                match &current_rest[pos..=pos] {
-                    "&" => {
-                        if logic_type == LogicType::Or {
-                            bail!("& and | mixed for {}", string);
-                        }
-                        logic_type = LogicType::And;
-                    }
-                    "|" => {
-                        if logic_type == LogicType::And {
-                            bail!("& and | mixed for {}", string);
-                        }
-                        logic_type = LogicType::Or;
-                    }
-                    _ => unreachable!(),
-                };
-                // add requirement to list
-                let requirement = current_rest[..pos].trim();
-                if requirement != "" {
-                    current_level.push(RequirementExpression::parse_base_requirement(
-                        requirement,
-                        area,
-                        macros,
-                        item_names,
-                        event_names,
-                    )?);
-                }
-                current_rest = current_rest[pos + 1..].trim();
-            } else {
-                // if they can't be found, the rest is one base expression and we're done
-                if current_rest != "" {
-                    current_level.push(RequirementExpression::parse_base_requirement(
-                        current_rest.trim(),
-                        area,
-                        macros,
-                        item_names,
-                        event_names,
-                    )?);
-                }
-                break;
-            }
-        }
-        if current_level.len() == 1 {
-            return Ok(current_level.remove(0));
-        }
-        match logic_type {
-            LogicType::And => {
-                return Ok(RequirementExpression::And(current_level));
-            }
-            LogicType::Or => {
-                return Ok(RequirementExpression::Or(current_level));
-            }
-            _ => {
-                bail!("no logic type, that should not be possible: {}", string)
-            }
-        }
+                    trick_names,
+                )
+            },
+        };
+        parser.parse()
     }
 
     fn parse_base_requirement(
@@ -306,6 +713,7 @@ impl RequirementExpression<'static> {
         macros: &[&HashMap<&str, RequirementExpression<'static>>],
         item_names: &HashMap<&str, ItemId>,
         event_names: &HashMap<&str, EventId>,
+        trick_names: &HashMap<&str, TrickId>,
     ) -> anyhow::Result<Self> {
         if s == "Nothing" {
             return Ok(RequirementExpression::Fixed(true));
@@ -337,18 +745,36 @@ impl RequirementExpression<'static> {
                 }
             }
         }
+        // item comparison, only `>=` since more items must never fail a requirement
+        let item_compare_re = Regex::new(r"^(.+?) *(>=|<=) *([0-9]+)$").unwrap();
+        if let Some(captures) = item_compare_re.captures(s) {
+            if let Some(item_id) = item_names.get(&captures[1]).cloned() {
+                let count: u8 = captures[3].parse().context("invalid count")?;
+                return match Comparison::parse(&captures[2]).unwrap() {
+                    Comparison::AtLeast => Ok(RequirementExpression::Item(item_id, count)),
+                    Comparison::AtMost => bail!("items can only be compared with >="),
+                };
+            }
+        }
         // items
         if let Some(item_id) = item_names.get(s).cloned() {
             return Ok(RequirementExpression::Item(item_id, 1));
         }
-        if s.contains("Trick") {
-            // return Ok(RequirementExpression::Option(s.to_string()));
-            return Ok(RequirementExpression::Fixed(true)); // TODO
+        if let Some(trick_name) = s.strip_suffix(" Trick") {
+            // tricks that aren't in the options can never be enabled, the linter reports them
+            return Ok(trick_names
+                .get(trick_name)
+                .map_or(RequirementExpression::Fixed(false), |trick| {
+                    RequirementExpression::Trick(*trick)
+                }));
         }
         if s.contains("Option") {
             let option_enabled_re = Regex::new("Option \"([a-z-]+)\" Enabled").unwrap();
             let option_is_re =
                 Regex::new("Option \"([a-z-]+)\" Is( Not)? \"([A-Za-z-]+)\"").unwrap();
+            let option_compare_re = Regex::new("Option \"([a-z-]+)\" (>=|<=) ([0-9]+)").unwrap();
+            let option_contains_re =
+                Regex::new("Option \"([a-z-]+)\" (Does Not )?Contains? \"([^\"]+)\"").unwrap();
             if let Some(enabled) = option_enabled_re.captures(s) {
                 let opt = enabled.get(1).unwrap();
                 return Ok(RequirementExpression::OptionEnabled {
@@ -362,6 +788,23 @@ impl RequirementExpression<'static> {
                 let value = option_is.get(3).unwrap().as_str().to_string();
                 return Ok(RequirementExpression::OptionIs { option, value, not });
             }
+            if let Some(option_compare) = option_compare_re.captures(s) {
+                let option = option_compare.get(1).unwrap().as_str().to_string();
+                let comparison =
+                    Comparison::parse(option_compare.get(2).unwrap().as_str()).unwrap();
+                let value = option_compare.get(3).unwrap().as_str().parse()?;
+                return Ok(RequirementExpression::OptionCompare {
+                    option,
+                    comparison,
+                    value,
+                });
+            }
+            if let Some(option_contains) = option_contains_re.captures(s) {
+                let option = option_contains.get(1).unwrap().as_str().to_string();
+                let not = option_contains.get(2).is_some();
+                let value = option_contains.get(3).unwrap().as_str().to_string();
+                return Ok(RequirementExpression::OptionContains { option, value, not });
+            }
         }
         if let Some(event_id) = event_names.get(s).cloned() {
             return Ok(RequirementExpression::Event(event_id));
@@ -369,3 +812,285 @@ impl RequirementExpression<'static> {
         bail!("unknown expression: {s}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOMB_BAG: ItemId = ItemId(0);
+    const WALLET: ItemId = ItemId(1);
+    const EVENT: EventId = EventId(0);
+    const TRICK: TrickId = TrickId(0);
+
+    fn parse(s: &str) -> anyhow::Result<RequirementExpression<'static>> {
+        let items = HashMap::from([("Bomb Bag", BOMB_BAG), ("Progressive Wallet", WALLET)]);
+        let events = HashMap::from([("Raise Lanayru Mining Facility", EVENT)]);
+        let tricks = HashMap::from([("Stuttersprint", TRICK)]);
+        RequirementExpression::parse_expression(s, None, &[], &items, &events, &tricks)
+    }
+
+    fn option_enabled(option: &str) -> RequirementExpression<'static> {
+        RequirementExpression::OptionEnabled {
+            option: option.to_string(),
+            enabled: true,
+        }
+    }
+
+    fn parse_error(s: &str) -> String {
+        match parse(s) {
+            Ok(_) => panic!("{s} shouldn't parse"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn item(id: ItemId) -> RequirementExpression<'static> {
+        RequirementExpression::Item(id, 1)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(
+            parse("Bomb Bag | Progressive Wallet & Raise Lanayru Mining Facility").unwrap()
+                == RequirementExpression::Or(vec![
+                    item(BOMB_BAG),
+                    RequirementExpression::And(vec![
+                        item(WALLET),
+                        RequirementExpression::Event(EVENT),
+                    ]),
+                ])
+        );
+        assert!(
+            parse("Bomb Bag & Progressive Wallet | Raise Lanayru Mining Facility").unwrap()
+                == RequirementExpression::Or(vec![
+                    RequirementExpression::And(vec![item(BOMB_BAG), item(WALLET)]),
+                    RequirementExpression::Event(EVENT),
+                ])
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert!(
+            parse("(Bomb Bag | Progressive Wallet) & Raise Lanayru Mining Facility").unwrap()
+                == RequirementExpression::And(vec![
+                    RequirementExpression::Or(vec![item(BOMB_BAG), item(WALLET)]),
+                    RequirementExpression::Event(EVENT),
+                ])
+        );
+        assert!(parse("((Bomb Bag))").unwrap() == item(BOMB_BAG));
+    }
+
+    #[test]
+    fn same_operators_are_flattened() {
+        assert!(
+            parse("Bomb Bag & Progressive Wallet x2 & Raise Lanayru Mining Facility").unwrap()
+                == RequirementExpression::And(vec![
+                    item(BOMB_BAG),
+                    RequirementExpression::Item(WALLET, 2),
+                    RequirementExpression::Event(EVENT),
+                ])
+        );
+        assert!(
+            parse("Bomb Bag | Progressive Wallet | Stuttersprint Trick").unwrap()
+                == RequirementExpression::Or(vec![
+                    item(BOMB_BAG),
+                    item(WALLET),
+                    RequirementExpression::Trick(TRICK),
+                ])
+        );
+    }
+
+    #[test]
+    fn operators_in_quotes_are_part_of_the_requirement() {
+        assert!(
+            parse(r#"Option "x" Contains "A & B (C)" | Bomb Bag"#).unwrap()
+                == RequirementExpression::Or(vec![
+                    RequirementExpression::OptionContains {
+                        option: "x".to_string(),
+                        value: "A & B (C)".to_string(),
+                        not: false,
+                    },
+                    item(BOMB_BAG),
+                ])
+        );
+    }
+
+    /// evaluates a requirement made of items and fixed values, `has` tells which items there are
+    fn check_tree(req: &RequirementExpression, has: &impl Fn(ItemId) -> bool) -> bool {
+        match req {
+            RequirementExpression::And(reqs) => reqs.iter().all(|req| check_tree(req, has)),
+            RequirementExpression::Or(reqs) => reqs.iter().any(|req| check_tree(req, has)),
+            RequirementExpression::Not(req) => !check_tree(req, has),
+            RequirementExpression::Item(item, _) => has(*item),
+            RequirementExpression::Fixed(value) => *value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn check_compiled(code: &[Instruction], has: &impl Fn(ItemId) -> bool) -> bool {
+        let mut pos = 0;
+        loop {
+            let instruction = &code[pos];
+            let passed = match instruction.test {
+                Some(RequirementExpression::Item(item, _)) => has(*item),
+                None => true,
+                _ => unreachable!("only base requirements are tested"),
+            };
+            let next = if passed {
+                instruction.if_true
+            } else {
+                instruction.if_false
+            };
+            match next {
+                Next::Jump(target) => {
+                    assert!(target > pos, "jumps have to go forward");
+                    pos = target;
+                }
+                Next::Done(result) => return result,
+            }
+        }
+    }
+
+    fn random_requirement(rng: &mut impl rand::Rng, depth: u8) -> RequirementExpression<'static> {
+        match rng.gen_range(0..if depth == 0 { 2 } else { 5 }) {
+            0 => RequirementExpression::Item(ItemId(rng.gen_range(0..ITEM_COUNT)), 1),
+            1 => RequirementExpression::Fixed(rng.gen()),
+            2 => RequirementExpression::Not(Box::new(random_requirement(rng, depth - 1))),
+            op => {
+                let reqs = (0..rng.gen_range(0..4))
+                    .map(|_| random_requirement(rng, depth - 1))
+                    .collect();
+                if op == 3 {
+                    RequirementExpression::And(reqs)
+                } else {
+                    RequirementExpression::Or(reqs)
+                }
+            }
+        }
+    }
+
+    const ITEM_COUNT: u16 = 5;
+
+    #[test]
+    fn compiled_matches_tree() {
+        use rand::SeedableRng;
+        let mut rng = rand_pcg::Pcg64::seed_from_u64(0);
+        for _ in 0..2000 {
+            let req = random_requirement(&mut rng, 4);
+            let code = req.compile();
+            // every combination of items
+            for items in 0..1 << ITEM_COUNT {
+                let has = |item: ItemId| items & (1 << item.0) != 0;
+                assert_eq!(check_tree(&req, &has), check_compiled(&code, &has));
+            }
+        }
+    }
+
+    #[test]
+    fn error_columns() {
+        assert_eq!(
+            parse_error("Bomb Bag &"),
+            "11: unexpected end of requirement"
+        );
+        assert_eq!(
+            parse_error("Bomb Bag & (Progressive Wallet | Raise Lanayru Mining Facility"),
+            "63: closing parenthesis for column 12 not found"
+        );
+        assert_eq!(
+            parse_error("Bomb Bag) & Progressive Wallet"),
+            "9: unmatched closing parenthesis"
+        );
+        assert_eq!(
+            parse_error("Bomb Bag & ()"),
+            "13: unexpected closing parenthesis"
+        );
+        assert_eq!(
+            parse_error("| Bomb Bag"),
+            "1: expected requirement before operator"
+        );
+        assert_eq!(
+            parse_error("Bomb Bag (Progressive Wallet)"),
+            "10: expected & or |"
+        );
+        assert_eq!(
+            parse_error("Bomb Bag & Bomb Bug"),
+            "12: unknown expression: Bomb Bug"
+        );
+        assert_eq!(parse_error(r#"Option "x Enabled"#), "8: unterminated quote");
+    }
+
+    #[test]
+    fn not_on_options_and_tricks() {
+        assert!(
+            parse(r#"not Option "hero-mode" Enabled"#).unwrap()
+                == RequirementExpression::Not(Box::new(option_enabled("hero-mode")))
+        );
+        assert!(
+            parse("not Stuttersprint Trick").unwrap()
+                == RequirementExpression::Not(Box::new(RequirementExpression::Trick(TRICK)))
+        );
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert!(
+            parse(r#"not Option "hero-mode" Enabled & Bomb Bag"#).unwrap()
+                == RequirementExpression::And(vec![
+                    RequirementExpression::Not(Box::new(option_enabled("hero-mode"))),
+                    RequirementExpression::Item(BOMB_BAG, 1),
+                ])
+        );
+        assert!(
+            parse(r#"not (Option "hero-mode" Enabled | Stuttersprint Trick)"#).unwrap()
+                == RequirementExpression::Not(Box::new(RequirementExpression::Or(vec![
+                    option_enabled("hero-mode"),
+                    RequirementExpression::Trick(TRICK),
+                ])))
+        );
+    }
+
+    #[test]
+    fn not_on_progress_is_rejected() {
+        assert!(parse_error("not Bomb Bag").starts_with("1: not can only be used"));
+        assert!(parse_error("not Raise Lanayru Mining Facility").starts_with("1: not"));
+        assert!(
+            parse_error(r#"Bomb Bag | not (Stuttersprint Trick & Progressive Wallet)"#)
+                .starts_with("12: not")
+        );
+    }
+
+    #[test]
+    fn item_comparisons() {
+        assert!(
+            parse("Progressive Wallet >= 2").unwrap() == RequirementExpression::Item(WALLET, 2)
+        );
+        assert!(
+            parse("Progressive Wallet>=2 & Bomb Bag").unwrap()
+                == RequirementExpression::And(vec![
+                    RequirementExpression::Item(WALLET, 2),
+                    RequirementExpression::Item(BOMB_BAG, 1),
+                ])
+        );
+        assert!(parse_error("Progressive Wallet <= 2").starts_with("1: items can only"));
+    }
+
+    #[test]
+    fn option_comparisons() {
+        assert!(
+            parse(r#"Option "required-dungeon-count" >= 3"#).unwrap()
+                == RequirementExpression::OptionCompare {
+                    option: "required-dungeon-count".to_string(),
+                    comparison: Comparison::AtLeast,
+                    value: 3,
+                }
+        );
+        assert!(
+            parse(r#"not Option "required-dungeon-count" <= 2"#).unwrap()
+                == RequirementExpression::Not(Box::new(RequirementExpression::OptionCompare {
+                    option: "required-dungeon-count".to_string(),
+                    comparison: Comparison::AtMost,
+                    value: 2,
+                }))
+        );
+    }
+}
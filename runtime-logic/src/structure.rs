@@ -15,6 +15,7 @@ pub struct LogicContext {
     pub locations: Vec<Location>,
     pub events: Vec<Event>,
     pub items: HashMap<ItemId, Item>,
+    pub tricks: Vec<Trick>,
 }
 
 impl LogicContext {
@@ -114,6 +115,8 @@ pub struct LocationId(pub u16);
 pub struct EventId(pub u16);
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ItemId(pub u16);
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TrickId(pub u16);
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum RequirementKey {
@@ -239,6 +242,10 @@ pub enum DoubleDoor {
 pub enum ConnectionShuffleType {
     Never,
     Other,
+    // through a trial gate, into its silent realm
+    TrialGate,
+    // out of a silent realm, back to its gate
+    TrialExit,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -390,6 +397,8 @@ pub struct Location {
     pub display_name: String,
     pub ident: String,
     pub kind: LocationKind,
+    /// check types from checks.yaml, each one a choice of the banned types option
+    pub types: Vec<String>,
     // TODO: technical detail enum
 }
 
@@ -479,3 +488,25 @@ impl ContextLoadable for ItemId {
             .map(|a| a.id)
     }
 }
+
+pub struct Trick {
+    pub id: TrickId,
+    pub name: String,
+    pub ident: String,
+}
+
+impl ContextLoadable for TrickId {
+    type Out = Trick;
+
+    fn ctx<'a>(&self, context: &'a LogicContext) -> &'a Self::Out {
+        &context.tricks[self.0 as usize]
+    }
+
+    fn name<'a>(&self, context: &'a LogicContext) -> &'a str {
+        &context.tricks[self.0 as usize].name
+    }
+
+    fn find(context: &LogicContext, name: &str) -> Option<Self> {
+        context.tricks.iter().find(|a| a.name == name).map(|a| a.id)
+    }
+}